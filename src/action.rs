@@ -0,0 +1,214 @@
+use crate::input::{Keyboard, Mouse, MouseButton};
+use crate::key::Key;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// bindings used when no config overrides them
+const DEFAULT_BINDINGS: &str = "
+pinch = mouse:left
+pin = key:Space
+move_anchor = mouse:right
+nudge_left = key:Left
+nudge_right = key:Right
+nudge_up = key:Up
+nudge_down = key:Down
+nudge_fast = key:LeftShift | key:RightShift
+pause = key:P
+tortilla_render = key:M
+particles_render = key:N
+outline = key:O
+pan = mouse:middle
+zoom_in = key:Equal | key:NumPadPlus
+zoom_out = key:Minus | key:NumPadMinus
+reset_view = key:Home
+debug = key:F1
+hud = key:H
+panel = key:Tab
+capture = key:F2
+dump_gif = key:F3
+save_snapshot = key:F5
+load_snapshot = key:F9
+layer_background = key:Key1
+layer_world = key:Key2
+layer_overlay = key:Key3
+layer_ui = key:Key4
+";
+
+#[derive(Debug)]
+pub enum ActionError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for ActionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ActionError::Io(err) => write!(f, "{err}"),
+            ActionError::Parse { line, message } => write!(f, "line {line}: {message}"),
+        }
+    }
+}
+
+impl From<io::Error> for ActionError {
+    fn from(err: io::Error) -> Self {
+        ActionError::Io(err)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Trigger {
+    Mouse(MouseButton),
+    Key(Key),
+}
+
+impl Trigger {
+    fn parse(text: &str) -> Option<Self> {
+        let (device, name) = text.trim().split_once(':')?;
+        match device.trim() {
+            "mouse" => MouseButton::from_name(name.trim()).map(Trigger::Mouse),
+            "key" => Key::from_name(name.trim()).map(Trigger::Key),
+            _ => None,
+        }
+    }
+
+    fn held(&self, mouse: &Mouse, keyboard: &Keyboard) -> bool {
+        match *self {
+            Trigger::Mouse(button) => mouse.button(button).held,
+            Trigger::Key(key) => keyboard.held(key),
+        }
+    }
+}
+
+/// triggers that must all be held together
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chord(Vec<Trigger>);
+
+impl Chord {
+    fn held(&self, mouse: &Mouse, keyboard: &Keyboard) -> bool {
+        !self.0.is_empty() && self.0.iter().all(|trigger| trigger.held(mouse, keyboard))
+    }
+
+    /// `self` holds every trigger of `other` and more
+    fn contains(&self, other: &Chord) -> bool {
+        other.0.iter().all(|trigger| self.0.contains(trigger))
+            && self.0.iter().any(|trigger| !other.0.contains(trigger))
+    }
+}
+
+#[derive(Default, Copy, Clone, Debug)]
+pub struct ActionState {
+    pub held: bool,
+    pub pressed: bool,
+    pub released: bool,
+}
+
+/// named actions, each bound to one or more chords
+///
+/// the config format is one `action = chord | chord` per line, a chord
+/// being `+` separated `mouse:<left|middle|right>` or `key:<name>` triggers
+#[derive(Clone, Debug)]
+pub struct ActionMap {
+    bindings: Vec<(String, Vec<Chord>)>,
+}
+
+impl Default for ActionMap {
+    fn default() -> Self {
+        ActionMap::parse(DEFAULT_BINDINGS).expect("invalid default bindings")
+    }
+}
+
+impl ActionMap {
+    pub fn parse(text: &str) -> Result<Self, ActionError> {
+        let mut map = ActionMap {
+            bindings: Vec::new(),
+        };
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: String| ActionError::Parse {
+                line: i + 1,
+                message,
+            };
+
+            let (name, chords) = line
+                .split_once('=')
+                .ok_or_else(|| error(format!("expected `action = binding`, found `{line}`")))?;
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(error(String::from("missing action name")));
+            }
+
+            let mut parsed = Vec::new();
+            for chord in chords.split('|') {
+                let triggers = chord
+                    .split('+')
+                    .map(|trigger| {
+                        Trigger::parse(trigger)
+                            .ok_or_else(|| error(format!("unknown trigger `{}`", trigger.trim())))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                parsed.push(Chord(triggers));
+            }
+            map.bind(name, parsed);
+        }
+        Ok(map)
+    }
+
+    /// default bindings, overridden by the actions listed in `path`
+    pub fn load(path: &Path) -> Result<Self, ActionError> {
+        let mut map = ActionMap::default();
+        for (name, chords) in ActionMap::parse(&fs::read_to_string(path)?)?.bindings {
+            map.bind(&name, chords);
+        }
+        Ok(map)
+    }
+
+    /// replace the chords bound to `name`
+    pub fn bind(&mut self, name: &str, chords: Vec<Chord>) {
+        match self.bindings.iter_mut().find(|(action, _)| action == name) {
+            Some((_, bound)) => *bound = chords,
+            None => self.bindings.push((name.to_string(), chords)),
+        }
+    }
+}
+
+/// per frame state of every bound action
+#[derive(Default, Clone, Debug)]
+pub struct Actions {
+    states: HashMap<String, ActionState>,
+}
+
+impl Actions {
+    /// a held chord gives way while a larger held chord, of any action,
+    /// contains its triggers: shift+S does not also trigger S
+    pub fn refresh(&mut self, map: &ActionMap, mouse: &Mouse, keyboard: &Keyboard) {
+        let held_chords: Vec<&Chord> = map
+            .bindings
+            .iter()
+            .flat_map(|(_, chords)| chords)
+            .filter(|chord| chord.held(mouse, keyboard))
+            .collect();
+        for (name, chords) in &map.bindings {
+            let held = chords.iter().any(|chord| {
+                held_chords.contains(&chord)
+                    && !held_chords.iter().any(|other| other.contains(chord))
+            });
+            let state = self.states.entry(name.clone()).or_default();
+            *state = ActionState {
+                held,
+                pressed: held && !state.held,
+                released: !held && state.held,
+            };
+        }
+    }
+
+    /// unbound actions are never held
+    pub fn get(&self, name: &str) -> ActionState {
+        self.states.get(name).copied().unwrap_or_default()
+    }
+}
@@ -0,0 +1,24 @@
+mod null;
+mod window;
+
+pub use null::NullBackend;
+pub use window::WindowBackend;
+
+use crate::frame::Frame;
+use crate::input::InputState;
+use crate::vector::Vector2D;
+
+pub trait Backend {
+    fn is_open(&self) -> bool;
+
+    fn size(&self) -> Vector2D<usize>;
+
+    fn poll_input(&mut self) -> InputState;
+
+    fn present(&mut self, frame: &Frame);
+
+    /// fixed duration of a frame, None to use the wall clock
+    fn frame_time(&self) -> Option<f32> {
+        None
+    }
+}
@@ -0,0 +1,67 @@
+use crate::backend::Backend;
+use crate::frame::Frame;
+use crate::input::InputState;
+use crate::vector::Vector2D;
+
+/// backend without any window, frames are kept in memory
+pub struct NullBackend {
+    frame: Frame,
+    frames_left: Option<usize>,
+    frame_time: Option<f32>,
+}
+
+impl NullBackend {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            frame: Frame::new(width, height),
+            frames_left: None,
+            frame_time: None,
+        }
+    }
+
+    /// close the backend after `frames` presented frames
+    pub fn with_frames(mut self, frames: usize) -> Self {
+        self.frames_left = Some(frames);
+        self
+    }
+
+    pub fn with_frame_time(mut self, dt: f32) -> Self {
+        self.frame_time = Some(dt);
+        self
+    }
+
+    /// last presented frame
+    pub fn frame(&self) -> &Frame {
+        &self.frame
+    }
+}
+
+impl Backend for NullBackend {
+    fn is_open(&self) -> bool {
+        self.frames_left != Some(0)
+    }
+
+    fn size(&self) -> Vector2D<usize> {
+        Vector2D {
+            x: self.frame.width,
+            y: self.frame.height,
+        }
+    }
+
+    fn poll_input(&mut self) -> InputState {
+        InputState::default()
+    }
+
+    fn present(&mut self, frame: &Frame) {
+        self.frame.width = frame.width;
+        self.frame.height = frame.height;
+        self.frame.buffer.clone_from(&frame.buffer);
+        if let Some(frames) = &mut self.frames_left {
+            *frames = frames.saturating_sub(1);
+        }
+    }
+
+    fn frame_time(&self) -> Option<f32> {
+        self.frame_time
+    }
+}
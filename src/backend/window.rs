@@ -0,0 +1,191 @@
+use crate::backend::Backend;
+use crate::frame::Frame;
+use crate::input::InputState;
+use crate::key::{Key, KeySet};
+use crate::vector::Vector2D;
+use minifb::{MouseButton, MouseMode, Window, WindowOptions};
+
+macro_rules! map_keys {
+    ($key:expr, $($name:ident),* $(,)?) => {
+        match $key {
+            $(minifb::Key::$name => Some(Key::$name),)*
+            _ => None,
+        }
+    };
+}
+
+fn map_key(key: minifb::Key) -> Option<Key> {
+    map_keys!(
+        key,
+        Key0,
+        Key1,
+        Key2,
+        Key3,
+        Key4,
+        Key5,
+        Key6,
+        Key7,
+        Key8,
+        Key9,
+        A,
+        B,
+        C,
+        D,
+        E,
+        F,
+        G,
+        H,
+        I,
+        J,
+        K,
+        L,
+        M,
+        N,
+        O,
+        P,
+        Q,
+        R,
+        S,
+        T,
+        U,
+        V,
+        W,
+        X,
+        Y,
+        Z,
+        F1,
+        F2,
+        F3,
+        F4,
+        F5,
+        F6,
+        F7,
+        F8,
+        F9,
+        F10,
+        F11,
+        F12,
+        F13,
+        F14,
+        F15,
+        Down,
+        Left,
+        Right,
+        Up,
+        Apostrophe,
+        Backquote,
+        Backslash,
+        Comma,
+        Equal,
+        LeftBracket,
+        Minus,
+        Period,
+        RightBracket,
+        Semicolon,
+        Slash,
+        Backspace,
+        Delete,
+        End,
+        Enter,
+        Escape,
+        Home,
+        Insert,
+        Menu,
+        PageDown,
+        PageUp,
+        Pause,
+        Space,
+        Tab,
+        NumLock,
+        CapsLock,
+        ScrollLock,
+        LeftShift,
+        RightShift,
+        LeftCtrl,
+        RightCtrl,
+        LeftAlt,
+        RightAlt,
+        LeftSuper,
+        RightSuper,
+        NumPad0,
+        NumPad1,
+        NumPad2,
+        NumPad3,
+        NumPad4,
+        NumPad5,
+        NumPad6,
+        NumPad7,
+        NumPad8,
+        NumPad9,
+        NumPadDot,
+        NumPadSlash,
+        NumPadAsterisk,
+        NumPadMinus,
+        NumPadPlus,
+        NumPadEnter,
+    )
+}
+
+pub struct WindowBackend {
+    window: Window,
+}
+
+impl WindowBackend {
+    pub fn new(title: &str, width: usize, height: usize, refresh: usize) -> Self {
+        let mut window = Window::new(title, width, height, WindowOptions::default())
+            .expect("WindowBackend::new(): Window::new failed");
+
+        window.set_target_fps(refresh);
+
+        Self { window }
+    }
+}
+
+impl WindowBackend {
+    fn keys(&self) -> KeySet {
+        let mut keys = KeySet::default();
+        for key in self.window.get_keys().into_iter().filter_map(map_key) {
+            keys.insert(key);
+        }
+        keys
+    }
+}
+
+impl Backend for WindowBackend {
+    fn is_open(&self) -> bool {
+        self.window.is_open()
+    }
+
+    fn size(&self) -> Vector2D<usize> {
+        let (x, y) = self.window.get_size();
+        Vector2D { x, y }
+    }
+
+    fn poll_input(&mut self) -> InputState {
+        InputState {
+            mouse_pos: self
+                .window
+                .get_mouse_pos(MouseMode::Clamp)
+                .map(|(x, y)| Vector2D { x, y }),
+            mouse_abs_pos: self
+                .window
+                .get_mouse_pos(MouseMode::Pass)
+                .map(|(x, y)| Vector2D { x, y }),
+            mouse_left: self.window.get_mouse_down(MouseButton::Left),
+            mouse_middle: self.window.get_mouse_down(MouseButton::Middle),
+            mouse_right: self.window.get_mouse_down(MouseButton::Right),
+            mouse_wheel: self
+                .window
+                .get_scroll_wheel()
+                .map(|(x, y)| Vector2D { x, y })
+                .unwrap_or_default(),
+            keys: self.keys(),
+        }
+    }
+
+    fn present(&mut self, frame: &Frame) {
+        self.window
+            .update_with_buffer(&frame.buffer, frame.width, frame.height)
+            .expect("Failed to update buffer");
+    }
+}
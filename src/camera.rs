@@ -0,0 +1,51 @@
+use crate::vector::Vector2D;
+
+const MIN_ZOOM: f32 = 0.1;
+const MAX_ZOOM: f32 = 20.0;
+
+/// maps world coordinates to screen pixels
+#[derive(Copy, Clone, Debug)]
+pub struct Camera {
+    /// world position shown at the top left corner of the screen
+    pub offset: Vector2D<f32>,
+    pub zoom: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            offset: Vector2D { x: 0.0, y: 0.0 },
+            zoom: 1.0,
+        }
+    }
+}
+
+impl Camera {
+    pub fn world_to_screen(&self, pos: Vector2D<f32>) -> Vector2D<f32> {
+        pos.sub(self.offset).vmul(self.zoom)
+    }
+
+    pub fn screen_to_world(&self, pos: Vector2D<f32>) -> Vector2D<f32> {
+        pos.vdiv(self.zoom).add(self.offset)
+    }
+
+    /// move the view by a screen space delta
+    pub fn pan(&mut self, delta: Vector2D<f32>) {
+        self.offset = self.offset.sub(delta.vdiv(self.zoom));
+    }
+
+    /// zoom by `factor` keeping the world point under `anchor` in place
+    pub fn zoom_at(&mut self, anchor: Vector2D<f32>, factor: f32) {
+        let world = self.screen_to_world(anchor);
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.offset = world.sub(anchor.vdiv(self.zoom));
+    }
+}
+
+/// what entities need to know to draw a frame
+#[derive(Copy, Clone, Debug, Default)]
+pub struct View {
+    pub camera: Camera,
+    /// how far the frame is between the last two physics steps (0.0..1.0)
+    pub alpha: f32,
+}
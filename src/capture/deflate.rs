@@ -0,0 +1,364 @@
+//! zlib stream writer with stored and fixed huffman deflate blocks
+
+const WINDOW_SIZE: usize = 32768;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const MAX_CHAIN: usize = 64;
+const HASH_SIZE: usize = 1 << 15;
+const MAX_STORED: usize = 65535;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+struct BitWriter {
+    bytes: Vec<u8>,
+    bit_buffer: u32,
+    bit_count: u32,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        Self {
+            bytes: Vec::new(),
+            bit_buffer: 0,
+            bit_count: 0,
+        }
+    }
+
+    /// write `count` bits of `value`, least significant bit first
+    fn bits(&mut self, value: u32, count: u32) {
+        self.bit_buffer |= value << self.bit_count;
+        self.bit_count += count;
+        while self.bit_count >= 8 {
+            self.bytes.push(self.bit_buffer as u8);
+            self.bit_buffer >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+
+    /// huffman codes are stored most significant bit first
+    fn code(&mut self, code: u32, len: u32) {
+        let mut reversed = 0;
+        for i in 0..len {
+            reversed |= ((code >> i) & 1) << (len - 1 - i);
+        }
+        self.bits(reversed, len);
+    }
+
+    fn align(&mut self) {
+        if self.bit_count > 0 {
+            self.bits(0, 8 - self.bit_count);
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.align();
+        self.bytes
+    }
+}
+
+fn write_literal(writer: &mut BitWriter, value: u16) {
+    match value {
+        0..=143 => writer.code(0x30 + value as u32, 8),
+        144..=255 => writer.code(0x190 + (value as u32 - 144), 9),
+        256..=279 => writer.code(value as u32 - 256, 7),
+        _ => writer.code(0xc0 + (value as u32 - 280), 8),
+    }
+}
+
+fn write_match(writer: &mut BitWriter, length: usize, distance: usize) {
+    let code = LENGTH_BASE
+        .iter()
+        .rposition(|&base| base as usize <= length)
+        .unwrap_or(0);
+    write_literal(writer, 257 + code as u16);
+    writer.bits(
+        (length - LENGTH_BASE[code] as usize) as u32,
+        LENGTH_EXTRA[code] as u32,
+    );
+
+    let code = DIST_BASE
+        .iter()
+        .rposition(|&base| base as usize <= distance)
+        .unwrap_or(0);
+    writer.code(code as u32, 5);
+    writer.bits(
+        (distance - DIST_BASE[code] as usize) as u32,
+        DIST_EXTRA[code] as u32,
+    );
+}
+
+fn hash(data: &[u8], i: usize) -> usize {
+    let value = (data[i] as usize) << 16 | (data[i + 1] as usize) << 8 | data[i + 2] as usize;
+    (value.wrapping_mul(2654435761) >> 7) % HASH_SIZE
+}
+
+/// single fixed huffman block with lz77 matches
+fn fixed_block(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::new();
+    writer.bits(1, 1);
+    writer.bits(1, 2);
+
+    let mut head = vec![usize::MAX; HASH_SIZE];
+    let mut prev = vec![usize::MAX; WINDOW_SIZE];
+    let insert = |head: &mut [usize], prev: &mut [usize], i: usize| {
+        if i + MIN_MATCH <= data.len() {
+            let h = hash(data, i);
+            prev[i % WINDOW_SIZE] = head[h];
+            head[h] = i;
+        }
+    };
+
+    let mut i = 0;
+    while i < data.len() {
+        let mut best_len = 0;
+        let mut best_dist = 0;
+
+        if i + MIN_MATCH <= data.len() {
+            let mut candidate = head[hash(data, i)];
+            let mut chain = 0;
+            let max_len = MAX_MATCH.min(data.len() - i);
+            while candidate != usize::MAX && i - candidate <= WINDOW_SIZE && chain < MAX_CHAIN {
+                let mut len = 0;
+                while len < max_len && data[candidate + len] == data[i + len] {
+                    len += 1;
+                }
+                if len > best_len {
+                    best_len = len;
+                    best_dist = i - candidate;
+                    if len == max_len {
+                        break;
+                    }
+                }
+                let next = prev[candidate % WINDOW_SIZE];
+                if next == usize::MAX || next >= candidate {
+                    break;
+                }
+                candidate = next;
+                chain += 1;
+            }
+        }
+
+        if best_len >= MIN_MATCH {
+            write_match(&mut writer, best_len, best_dist);
+            for j in i..i + best_len {
+                insert(&mut head, &mut prev, j);
+            }
+            i += best_len;
+        } else {
+            write_literal(&mut writer, data[i] as u16);
+            insert(&mut head, &mut prev, i);
+            i += 1;
+        }
+    }
+
+    write_literal(&mut writer, 256);
+    writer.finish()
+}
+
+fn stored_blocks(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + data.len() / MAX_STORED * 5 + 5);
+    let mut chunks = data.chunks(MAX_STORED).peekable();
+    if chunks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(chunk) = chunks.next() {
+        let last = chunks.peek().is_none();
+        let len = chunk.len() as u16;
+        out.push(last as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(chunk);
+    }
+    out
+}
+
+pub fn adler32(data: &[u8]) -> u32 {
+    let mut a: u32 = 1;
+    let mut b: u32 = 0;
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+/// deflate `data` into a zlib stream, falling back to stored blocks
+/// when compression does not pay off
+pub fn zlib(data: &[u8]) -> Vec<u8> {
+    let compressed = fixed_block(data);
+    let body = if compressed.len() < data.len() {
+        compressed
+    } else {
+        stored_blocks(data)
+    };
+
+    let mut out = Vec::with_capacity(body.len() + 6);
+    out.extend_from_slice(&[0x78, 0x01]);
+    out.extend_from_slice(&body);
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    struct BitReader<'a> {
+        bytes: &'a [u8],
+        pos: usize,
+    }
+
+    impl BitReader<'_> {
+        fn bits(&mut self, count: u32) -> u32 {
+            let mut value = 0;
+            for i in 0..count {
+                let bit = (self.bytes[self.pos / 8] >> (self.pos % 8)) & 1;
+                value |= (bit as u32) << i;
+                self.pos += 1;
+            }
+            value
+        }
+
+        /// huffman codes come most significant bit first
+        fn code(&mut self, len: u32) -> u32 {
+            (0..len).fold(0, |code, _| (code << 1) | self.bits(1))
+        }
+
+        fn literal(&mut self) -> u16 {
+            let code = self.code(7);
+            if code <= 0x17 {
+                return 256 + code as u16;
+            }
+            let code = (code << 1) | self.bits(1);
+            match code {
+                0x30..=0xbf => (code - 0x30) as u16,
+                0xc0..=0xc7 => (280 + code - 0xc0) as u16,
+                _ => (144 + ((code << 1) | self.bits(1)) - 0x190) as u16,
+            }
+        }
+    }
+
+    /// inflate a zlib stream made of stored and fixed huffman blocks
+    pub(crate) fn inflate(stream: &[u8]) -> Vec<u8> {
+        assert_eq!(u16::from_be_bytes([stream[0], stream[1]]) % 31, 0);
+        let mut reader = BitReader {
+            bytes: &stream[2..stream.len() - 4],
+            pos: 0,
+        };
+        let mut out: Vec<u8> = Vec::new();
+        loop {
+            let last = reader.bits(1) == 1;
+            match reader.bits(2) {
+                0 => {
+                    reader.pos = reader.pos.div_ceil(8) * 8;
+                    let len = reader.bits(16);
+                    assert_eq!(reader.bits(16), !len & 0xffff);
+                    for _ in 0..len {
+                        out.push(reader.bits(8) as u8);
+                    }
+                }
+                1 => loop {
+                    let symbol = reader.literal();
+                    match symbol {
+                        0..=255 => out.push(symbol as u8),
+                        256 => break,
+                        _ => {
+                            let code = (symbol - 257) as usize;
+                            let length = LENGTH_BASE[code] as usize
+                                + reader.bits(LENGTH_EXTRA[code] as u32) as usize;
+                            let code = reader.code(5) as usize;
+                            let distance = DIST_BASE[code] as usize
+                                + reader.bits(DIST_EXTRA[code] as u32) as usize;
+                            for _ in 0..length {
+                                out.push(out[out.len() - distance]);
+                            }
+                        }
+                    }
+                },
+                kind => panic!("unexpected block type {kind}"),
+            }
+            if last {
+                break;
+            }
+        }
+        let checksum = u32::from_be_bytes(stream[stream.len() - 4..].try_into().unwrap());
+        assert_eq!(checksum, adler32(&out));
+        out
+    }
+
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 0x2545f491u32;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn adler32_matches_reference() {
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e60398);
+    }
+
+    #[test]
+    fn empty_input_round_trips() {
+        assert_eq!(inflate(&zlib(&[])), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn repetitive_input_uses_a_fixed_block() {
+        let data: Vec<u8> = b"slime slime slime tortilla "
+            .iter()
+            .copied()
+            .cycle()
+            .take(100_000)
+            .collect();
+        let stream = zlib(&data);
+        assert!(stream.len() < data.len() / 10);
+        assert_eq!(stream[2] & 0b111, 0b011);
+        assert_eq!(inflate(&stream), data);
+    }
+
+    #[test]
+    fn every_match_length_and_distance_round_trips() {
+        let mut data = noise(40_000);
+        for len in MIN_MATCH..=MAX_MATCH + 10 {
+            let start = data.len() - len * 97 % 30_000 - len;
+            let copy = data[start..start + len].to_vec();
+            data.extend_from_slice(&copy);
+        }
+        let stream = zlib(&data);
+        assert_eq!(stream[2] & 0b111, 0b011);
+        assert_eq!(inflate(&stream), data);
+    }
+
+    #[test]
+    fn noise_falls_back_to_stored_blocks() {
+        let data = noise(MAX_STORED * 2 + 10);
+        let stream = zlib(&data);
+        assert_eq!(stream[2] & 0b111, 0b000);
+        assert_eq!(inflate(&stream), data);
+    }
+}
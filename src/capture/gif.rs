@@ -0,0 +1,387 @@
+//! animated gif encoder: median cut palette and lzw compression
+
+use crate::rgb;
+use std::collections::HashMap;
+
+const MAX_COLORS: usize = 256;
+const MIN_CODE_SIZE: u32 = 8;
+const MAX_CODE_SIZE: u32 = 12;
+const MAX_CODES: u16 = 1 << MAX_CODE_SIZE;
+
+fn channels(color: u32) -> [u8; 3] {
+    [(color >> 16) as u8, (color >> 8) as u8, color as u8]
+}
+
+/// widest channel of a box of colors and its range
+fn widest_channel(colors: &[(u32, usize)]) -> (usize, u8) {
+    let mut min = [255u8; 3];
+    let mut max = [0u8; 3];
+    for &(color, _) in colors {
+        let c = channels(color);
+        for i in 0..3 {
+            min[i] = min[i].min(c[i]);
+            max[i] = max[i].max(c[i]);
+        }
+    }
+    (0..3)
+        .map(|i| (i, max[i] - min[i]))
+        .max_by_key(|&(_, range)| range)
+        .unwrap_or((0, 0))
+}
+
+fn average(colors: &[(u32, usize)]) -> u32 {
+    let mut sum = [0usize; 3];
+    let mut total = 0;
+    for &(color, count) in colors {
+        let c = channels(color);
+        for i in 0..3 {
+            sum[i] += c[i] as usize * count;
+        }
+        total += count;
+    }
+    let total = total.max(1);
+    rgb!(sum[0] / total, sum[1] / total, sum[2] / total) as u32
+}
+
+/// median cut over the color histogram, exact when there are few colors
+fn quantize(histogram: HashMap<u32, usize>) -> Vec<u32> {
+    let mut colors: Vec<(u32, usize)> = histogram.into_iter().collect();
+    colors.sort_unstable();
+    if colors.len() <= MAX_COLORS {
+        return colors.into_iter().map(|(color, _)| color).collect();
+    }
+
+    let mut boxes = vec![colors];
+    while boxes.len() < MAX_COLORS {
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, colors)| colors.len() > 1)
+            .map(|(i, colors)| (i, widest_channel(colors)))
+            .max_by_key(|&(_, (_, range))| range);
+        let (index, (channel, _)) = match widest {
+            Some(widest) => widest,
+            None => break,
+        };
+
+        let mut colors = boxes.swap_remove(index);
+        colors.sort_unstable_by_key(|&(color, _)| channels(color)[channel]);
+        let half = colors.iter().map(|&(_, count)| count).sum::<usize>() / 2;
+        let mut seen = 0;
+        let mut split = 1;
+        for (i, &(_, count)) in colors.iter().enumerate() {
+            seen += count;
+            if seen >= half {
+                split = (i + 1).clamp(1, colors.len() - 1);
+                break;
+            }
+        }
+        let upper = colors.split_off(split);
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+    boxes.iter().map(|colors| average(colors)).collect()
+}
+
+fn nearest(palette: &[u32], color: u32) -> u8 {
+    let c = channels(color);
+    let mut best = (0, i32::MAX);
+    for (i, &entry) in palette.iter().enumerate() {
+        let e = channels(entry);
+        let dist: i32 = (0..3)
+            .map(|k| (c[k] as i32 - e[k] as i32) * (c[k] as i32 - e[k] as i32))
+            .sum();
+        if dist < best.1 {
+            best = (i, dist);
+        }
+    }
+    best.0 as u8
+}
+
+struct LzwWriter {
+    bytes: Vec<u8>,
+    bit_buffer: u32,
+    bit_count: u32,
+}
+
+impl LzwWriter {
+    fn code(&mut self, code: u16, size: u32) {
+        self.bit_buffer |= (code as u32) << self.bit_count;
+        self.bit_count += size;
+        while self.bit_count >= 8 {
+            self.bytes.push(self.bit_buffer as u8);
+            self.bit_buffer >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bit_count > 0 {
+            self.bytes.push(self.bit_buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+fn lzw(indices: &[u8]) -> Vec<u8> {
+    let clear: u16 = 1 << MIN_CODE_SIZE;
+    let end = clear + 1;
+
+    let mut writer = LzwWriter {
+        bytes: Vec::new(),
+        bit_buffer: 0,
+        bit_count: 0,
+    };
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut code_size = MIN_CODE_SIZE + 1;
+    let mut next = end + 1;
+
+    writer.code(clear, code_size);
+    let mut prefix = match indices.first() {
+        Some(&first) => first as u16,
+        None => {
+            writer.code(end, code_size);
+            return writer.finish();
+        }
+    };
+
+    for &index in &indices[1..] {
+        if let Some(&code) = table.get(&(prefix, index)) {
+            prefix = code;
+            continue;
+        }
+        writer.code(prefix, code_size);
+        if next < MAX_CODES {
+            table.insert((prefix, index), next);
+            next += 1;
+            if next as u32 > (1 << code_size) && code_size < MAX_CODE_SIZE {
+                code_size += 1;
+            }
+        } else {
+            writer.code(clear, code_size);
+            table.clear();
+            code_size = MIN_CODE_SIZE + 1;
+            next = end + 1;
+        }
+        prefix = index as u16;
+    }
+    writer.code(prefix, code_size);
+    writer.code(end, code_size);
+    writer.finish()
+}
+
+fn write_sub_blocks(out: &mut Vec<u8>, data: &[u8]) {
+    for block in data.chunks(255) {
+        out.push(block.len() as u8);
+        out.extend_from_slice(block);
+    }
+    out.push(0);
+}
+
+/// looping gif from rgb buffers, each frame comes with its delay in
+/// hundredths of a second
+pub fn encode(width: usize, height: usize, frames: &[(&[u32], u16)]) -> Vec<u8> {
+    let mut histogram: HashMap<u32, usize> = HashMap::new();
+    for (buffer, _) in frames {
+        for &pixel in buffer.iter() {
+            *histogram.entry(pixel & 0xffffff).or_insert(0) += 1;
+        }
+    }
+    let palette = quantize(histogram);
+
+    let mut out = Vec::new();
+    out.extend_from_slice(b"GIF89a");
+    out.extend_from_slice(&(width as u16).to_le_bytes());
+    out.extend_from_slice(&(height as u16).to_le_bytes());
+    // global color table of 256 entries, 8 bits per channel
+    out.extend_from_slice(&[0xf7, 0, 0]);
+    for i in 0..MAX_COLORS {
+        out.extend_from_slice(&channels(palette.get(i).copied().unwrap_or(0)));
+    }
+    // NETSCAPE2.0 application extension, loop forever
+    out.extend_from_slice(&[0x21, 0xff, 0x0b]);
+    out.extend_from_slice(b"NETSCAPE2.0");
+    out.extend_from_slice(&[0x03, 0x01, 0x00, 0x00, 0x00]);
+
+    let mut lookup: HashMap<u32, u8> = HashMap::new();
+    for (buffer, delay) in frames {
+        out.extend_from_slice(&[0x21, 0xf9, 0x04, 0x04]);
+        out.extend_from_slice(&delay.to_le_bytes());
+        out.extend_from_slice(&[0x00, 0x00]);
+
+        out.push(0x2c);
+        out.extend_from_slice(&[0, 0, 0, 0]);
+        out.extend_from_slice(&(width as u16).to_le_bytes());
+        out.extend_from_slice(&(height as u16).to_le_bytes());
+        out.push(0);
+
+        let indices: Vec<u8> = buffer
+            .iter()
+            .map(|&pixel| {
+                let color = pixel & 0xffffff;
+                *lookup
+                    .entry(color)
+                    .or_insert_with(|| nearest(&palette, color))
+            })
+            .collect();
+        out.push(MIN_CODE_SIZE as u8);
+        write_sub_blocks(&mut out, &lzw(&indices));
+    }
+
+    out.push(0x3b);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// lzw codes back to palette indices, the way gif decoders read them
+    fn unlzw(data: &[u8]) -> Vec<u8> {
+        let clear = 1usize << MIN_CODE_SIZE;
+        let end = clear + 1;
+        let reset = || -> Vec<Vec<u8>> { (0..=end).map(|i| vec![i as u8]).collect() };
+
+        let mut table = reset();
+        let mut code_size = MIN_CODE_SIZE + 1;
+        let mut prev: Option<Vec<u8>> = None;
+        let mut out = Vec::new();
+        let mut pos = 0;
+        loop {
+            let mut code = 0;
+            for i in 0..code_size as usize {
+                let bit = (data[(pos + i) / 8] >> ((pos + i) % 8)) & 1;
+                code |= (bit as usize) << i;
+            }
+            pos += code_size as usize;
+
+            if code == clear {
+                table = reset();
+                code_size = MIN_CODE_SIZE + 1;
+                prev = None;
+                continue;
+            }
+            if code == end {
+                return out;
+            }
+            let entry = match (table.get(code), &prev) {
+                (Some(entry), _) => entry.clone(),
+                (None, Some(prev)) if code == table.len() => {
+                    let mut entry = prev.clone();
+                    entry.push(prev[0]);
+                    entry
+                }
+                _ => panic!("code {code} out of the table"),
+            };
+            out.extend_from_slice(&entry);
+            if let Some(mut prev) = prev.take()
+                && table.len() < MAX_CODES as usize
+            {
+                prev.push(entry[0]);
+                table.push(prev);
+                if table.len() == 1 << code_size && code_size < MAX_CODE_SIZE {
+                    code_size += 1;
+                }
+            }
+            prev = Some(entry);
+        }
+    }
+
+    fn noise(len: usize, modulo: u32) -> Vec<u8> {
+        let mut state = 0x9e3779b9u32;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                (state % modulo) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lzw_round_trips() {
+        assert_eq!(unlzw(&lzw(&[])), Vec::<u8>::new());
+        assert_eq!(unlzw(&lzw(&[7])), [7]);
+        let runs: Vec<u8> = (0..20_000).map(|i| (i / 300) as u8).collect();
+        assert_eq!(unlzw(&lzw(&runs)), runs);
+    }
+
+    #[test]
+    fn lzw_round_trips_through_full_tables() {
+        // enough distinct strings to fill the table and clear it many times
+        let indices = noise(200_000, 256);
+        assert_eq!(unlzw(&lzw(&indices)), indices);
+        let indices = noise(200_000, 5);
+        assert_eq!(unlzw(&lzw(&indices)), indices);
+    }
+
+    #[test]
+    fn few_colors_are_kept_exactly() {
+        let histogram = HashMap::from([(0xff0000, 10), (0x00ff00, 1), (0x123456, 4)]);
+        assert_eq!(quantize(histogram), [0x00ff00, 0x123456, 0xff0000]);
+    }
+
+    #[test]
+    fn median_cut_stays_close_to_every_color() {
+        let histogram: HashMap<u32, usize> = (0..4096u32)
+            .map(|i| {
+                (
+                    ((i & 0xf) << 20) | (((i >> 4) & 0xf) << 12) | ((i >> 8) << 4),
+                    1,
+                )
+            })
+            .collect();
+        let palette = quantize(histogram.clone());
+        assert_eq!(palette.len(), MAX_COLORS);
+        for &color in histogram.keys() {
+            let found = channels(palette[nearest(&palette, color) as usize]);
+            for (a, b) in channels(color).iter().zip(found) {
+                assert!(a.abs_diff(b) <= 24, "{color:06x} mapped to {found:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn frames_decode_to_their_pixels() {
+        let (width, height) = (13, 9);
+        let first: Vec<u32> = (0..width * height)
+            .map(|i| (i as u32 % 7) * 0x102030)
+            .collect();
+        let second: Vec<u32> = first.iter().rev().copied().collect();
+        let gif = encode(width, height, &[(&first, 4), (&second, 9)]);
+
+        assert_eq!(&gif[..6], b"GIF89a");
+        assert_eq!(gif[6..10], [13, 0, 9, 0]);
+        assert_eq!(*gif.last().unwrap(), 0x3b);
+        let palette: Vec<u32> = gif[13..13 + MAX_COLORS * 3]
+            .chunks(3)
+            .map(|c| (c[0] as u32) << 16 | (c[1] as u32) << 8 | c[2] as u32)
+            .collect();
+
+        // skip the netscape extension, then read each frame
+        let mut pos = 13 + MAX_COLORS * 3 + 19;
+        for (pixels, delay) in [(&first, 4u16), (&second, 9)] {
+            assert_eq!(gif[pos..pos + 4], [0x21, 0xf9, 0x04, 0x04]);
+            assert_eq!(u16::from_le_bytes([gif[pos + 4], gif[pos + 5]]), delay);
+            pos += 8;
+            assert_eq!(gif[pos], 0x2c);
+            pos += 10;
+            assert_eq!(gif[pos], MIN_CODE_SIZE as u8);
+            pos += 1;
+            let mut data = Vec::new();
+            while gif[pos] != 0 {
+                let len = gif[pos] as usize;
+                data.extend_from_slice(&gif[pos + 1..pos + 1 + len]);
+                pos += 1 + len;
+            }
+            pos += 1;
+            let decoded: Vec<u32> = unlzw(&data)
+                .iter()
+                .map(|&index| palette[index as usize])
+                .collect();
+            assert_eq!(&decoded, pixels);
+        }
+        assert_eq!(pos, gif.len() - 1);
+    }
+}
@@ -0,0 +1,111 @@
+mod deflate;
+mod gif;
+mod png;
+mod ppm;
+mod recorder;
+
+pub use recorder::GifRecorder;
+
+use crate::frame::Frame;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ImageFormat {
+    Ppm,
+    Png,
+}
+
+impl ImageFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "ppm" => Some(ImageFormat::Ppm),
+            "png" => Some(ImageFormat::Png),
+            _ => None,
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Ppm => "ppm",
+            ImageFormat::Png => "png",
+        }
+    }
+
+    fn encode(&self, frame: &Frame) -> Vec<u8> {
+        match self {
+            ImageFormat::Ppm => ppm::encode(frame),
+            ImageFormat::Png => png::encode(frame),
+        }
+    }
+}
+
+/// writes frames to `dir/frame_000000.<ext>`, numbered in capture order
+pub struct Capture {
+    dir: PathBuf,
+    formats: Vec<ImageFormat>,
+    every: usize,
+    enabled: bool,
+    frame_count: usize,
+    index: usize,
+}
+
+impl Capture {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            formats: vec![ImageFormat::Ppm, ImageFormat::Png],
+            every: 1,
+            enabled: false,
+            frame_count: 0,
+            index: 0,
+        }
+    }
+
+    pub fn with_formats(mut self, formats: Vec<ImageFormat>) -> Self {
+        self.formats = formats;
+        self
+    }
+
+    /// only keep one frame out of `every`
+    pub fn with_every(mut self, every: usize) -> Self {
+        self.every = every.max(1);
+        self
+    }
+
+    pub fn with_enabled(mut self) -> Self {
+        self.enabled = true;
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+        self.frame_count = 0;
+    }
+
+    pub fn record(&mut self, frame: &Frame) -> io::Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        let keep = self.frame_count.is_multiple_of(self.every);
+        self.frame_count += 1;
+        if !keep {
+            return Ok(());
+        }
+
+        fs::create_dir_all(&self.dir)?;
+        for format in &self.formats {
+            let path = self
+                .dir
+                .join(format!("frame_{:06}.{}", self.index, format.extension()));
+            fs::write(path, format.encode(frame))?;
+        }
+        self.index += 1;
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+use crate::capture::deflate;
+use crate::frame::Frame;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xffffffffu32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb88320 & mask);
+        }
+    }
+    !crc
+}
+
+fn write_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// 8 bit rgb png, every scanline uses the `Sub` filter
+pub fn encode(frame: &Frame) -> Vec<u8> {
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(frame.width as u32).to_be_bytes());
+    header.extend_from_slice(&(frame.height as u32).to_be_bytes());
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    let mut raw = Vec::with_capacity((frame.width * 3 + 1) * frame.height);
+    for row in frame.buffer.chunks(frame.width.max(1)) {
+        raw.push(1);
+        let mut last = [0u8; 3];
+        for &pixel in row {
+            let rgb = [(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8];
+            for c in 0..3 {
+                raw.push(rgb[c].wrapping_sub(last[c]));
+            }
+            last = rgb;
+        }
+    }
+
+    let mut out = Vec::new();
+    out.extend_from_slice(&SIGNATURE);
+    write_chunk(&mut out, b"IHDR", &header);
+    write_chunk(&mut out, b"IDAT", &deflate::zlib(&raw));
+    write_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::deflate::tests::inflate;
+
+    /// (kind, data) of every chunk, checking their crc
+    fn chunks(png: &[u8]) -> Vec<([u8; 4], &[u8])> {
+        assert_eq!(png[..8], SIGNATURE);
+        let mut chunks = Vec::new();
+        let mut rest = &png[8..];
+        while !rest.is_empty() {
+            let len = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            let crc = u32::from_be_bytes(rest[8 + len..12 + len].try_into().unwrap());
+            assert_eq!(crc, crc32(&rest[4..8 + len]));
+            chunks.push((rest[4..8].try_into().unwrap(), &rest[8..8 + len]));
+            rest = &rest[12 + len..];
+        }
+        chunks
+    }
+
+    #[test]
+    fn crc32_matches_reference() {
+        assert_eq!(crc32(b"IEND"), 0xae426082);
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    }
+
+    #[test]
+    fn pixels_survive_encoding() {
+        let mut frame = Frame::new(7, 5);
+        for (i, pixel) in frame.buffer.iter_mut().enumerate() {
+            *pixel = (i as u32).wrapping_mul(0x9e3779b9) & 0xffffff;
+        }
+        let png = encode(&frame);
+        let chunks = chunks(&png);
+        let kinds: Vec<_> = chunks.iter().map(|(kind, _)| kind).collect();
+        assert_eq!(kinds, [b"IHDR", b"IDAT", b"IEND"]);
+        assert_eq!(chunks[0].1, [0, 0, 0, 7, 0, 0, 0, 5, 8, 2, 0, 0, 0]);
+
+        let raw = inflate(chunks[1].1);
+        assert_eq!(raw.len(), (7 * 3 + 1) * 5);
+        for (y, row) in raw.chunks(7 * 3 + 1).enumerate() {
+            assert_eq!(row[0], 1);
+            let mut last = [0u8; 3];
+            for x in 0..7 {
+                let rgb: [u8; 3] =
+                    std::array::from_fn(|c| row[1 + x * 3 + c].wrapping_add(last[c]));
+                let pixel = frame.buffer[y * 7 + x];
+                assert_eq!(rgb, [(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8]);
+                last = rgb;
+            }
+        }
+    }
+}
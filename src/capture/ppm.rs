@@ -0,0 +1,11 @@
+use crate::frame::Frame;
+
+/// binary (P6) portable pixmap
+pub fn encode(frame: &Frame) -> Vec<u8> {
+    let mut out = format!("P6\n{} {}\n255\n", frame.width, frame.height).into_bytes();
+    out.reserve(frame.buffer.len() * 3);
+    for &pixel in &frame.buffer {
+        out.extend_from_slice(&[(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8]);
+    }
+    out
+}
@@ -0,0 +1,110 @@
+use crate::capture::gif;
+use crate::frame::Frame;
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+struct Clip {
+    buffer: Vec<u32>,
+    dt: f32,
+}
+
+/// rolling buffer of the last frames, dumped as an animated gif on demand
+pub struct GifRecorder {
+    dir: PathBuf,
+    clips: VecDeque<Clip>,
+    capacity: usize,
+    every: usize,
+    scale: usize,
+    frame_count: usize,
+    elapsed: f32,
+    width: usize,
+    height: usize,
+    index: usize,
+}
+
+impl GifRecorder {
+    pub fn new(dir: impl Into<PathBuf>, capacity: usize) -> Self {
+        Self {
+            dir: dir.into(),
+            clips: VecDeque::with_capacity(capacity),
+            capacity,
+            every: 1,
+            scale: 1,
+            frame_count: 0,
+            elapsed: 0.0,
+            width: 0,
+            height: 0,
+            index: 0,
+        }
+    }
+
+    /// only keep one frame out of `every`
+    pub fn with_every(mut self, every: usize) -> Self {
+        self.every = every.max(1);
+        self
+    }
+
+    /// downscale kept frames by `scale` to bound memory
+    pub fn with_scale(mut self, scale: usize) -> Self {
+        self.scale = scale.max(1);
+        self
+    }
+
+    pub fn push(&mut self, frame: &Frame, dt: f32) {
+        self.elapsed += dt;
+        let keep = self.frame_count.is_multiple_of(self.every);
+        self.frame_count += 1;
+        if !keep || self.capacity == 0 {
+            return;
+        }
+
+        let width = frame.width / self.scale;
+        let height = frame.height / self.scale;
+        if width != self.width || height != self.height {
+            self.clips.clear();
+            self.width = width;
+            self.height = height;
+        }
+
+        let mut buffer = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let row = y * self.scale * frame.width;
+            for x in 0..width {
+                buffer.push(frame.buffer[row + x * self.scale]);
+            }
+        }
+
+        if self.clips.len() == self.capacity {
+            self.clips.pop_front();
+        }
+        self.clips.push_back(Clip {
+            buffer,
+            dt: self.elapsed,
+        });
+        self.elapsed = 0.0;
+    }
+
+    /// write the buffered frames to `dir/clip_000.gif`
+    pub fn dump(&mut self) -> io::Result<PathBuf> {
+        // gif delays are in hundredths of a second, carry the rounding
+        let mut carry = 0.0;
+        let frames: Vec<(&[u32], u16)> = self
+            .clips
+            .iter()
+            .map(|clip| {
+                let exact = clip.dt * 100.0 + carry;
+                let delay = exact.round().max(1.0);
+                carry = exact - delay;
+                (clip.buffer.as_slice(), delay as u16)
+            })
+            .collect();
+
+        fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(format!("clip_{:03}.gif", self.index));
+        fs::write(&path, gif::encode(self.width, self.height, &frames))?;
+        self.index += 1;
+        Ok(path)
+    }
+}
@@ -0,0 +1,325 @@
+use slime::capture::ImageFormat;
+use slime::scene::{MAX_RADIUS, Override, Value};
+use std::fmt;
+use std::str::FromStr;
+
+pub const USAGE: &str = "\
+usage: slime [options]
+
+scene:
+  --scene <path>            load a scene file and reload it when it changes
+  --size <width>x<height>   window size, replaces the scene one
+  --fps <hz>                target frame rate of the window
+  --particles <count>       particle count of every particle system
+  --radius <radius>         radius of every tortilla, at most 200
+  --seed <n>                scatter particles off their grid, same seed same layout
+
+run:
+  --headless <frames>       run without a window and print a checksum of the last frame
+  --layer-frames            draw each layer offscreen and composite them
+  --restore <path>          start from a snapshot, F5/F9 then use that file
+  --actions <path>          rebind actions, unlisted ones keep their default
+  --replay <path>           play a recorded session back
+  --record <path>           record this session
+
+capture:
+  --capture <dir>           record frames into <dir> from the start
+  --capture-every <n>       keep one frame out of <n>
+  --capture-format <fmt>    ppm, png or both
+  --gif-frames <n>          frames dumped into a gif by F3
+
+  -h, --help                print this help
+";
+
+#[derive(Debug)]
+pub struct CliError(String);
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// every option of the command line, `None` when not given
+#[derive(Default, Debug)]
+pub struct Options {
+    pub help: bool,
+    pub scene: Option<String>,
+    pub size: Option<(usize, usize)>,
+    pub fps: Option<usize>,
+    pub particles: Option<usize>,
+    pub radius: Option<f32>,
+    pub seed: Option<u32>,
+    pub headless: Option<usize>,
+    pub layer_frames: bool,
+    pub restore: Option<String>,
+    pub actions: Option<String>,
+    pub replay: Option<String>,
+    pub record: Option<String>,
+    pub capture: Option<String>,
+    pub capture_every: Option<usize>,
+    pub capture_formats: Option<Vec<ImageFormat>>,
+    pub gif_frames: Option<usize>,
+}
+
+impl Options {
+    /// `args` without the program name, values follow their option as the
+    /// next argument or after `=`
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, CliError> {
+        let mut options = Options::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (name, inline) = match arg.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => {
+                    (name.to_string(), Some(value.to_string()))
+                }
+                _ => (arg, None),
+            };
+            match name.as_str() {
+                "-h" | "--help" => {
+                    options.help = true;
+                    continue;
+                }
+                "--layer-frames" => {
+                    options.layer_frames = true;
+                    continue;
+                }
+                _ => {}
+            }
+            if !name.starts_with('-') {
+                return Err(CliError(format!("unexpected argument `{name}`")));
+            }
+
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| CliError(format!("`{name}` expects a value")))
+            };
+            match name.as_str() {
+                "--scene" => options.scene = Some(value()?),
+                "--size" => options.size = Some(parse_size(&name, &value()?)?),
+                "--fps" => options.fps = Some(positive(&name, &value()?)?),
+                "--particles" => options.particles = Some(positive(&name, &value()?)?),
+                "--radius" => options.radius = Some(at_most(&name, &value()?, MAX_RADIUS)?),
+                "--seed" => options.seed = Some(number(&name, &value()?)?),
+                "--headless" => options.headless = Some(number(&name, &value()?)?),
+                "--restore" => options.restore = Some(value()?),
+                "--actions" => options.actions = Some(value()?),
+                "--replay" => options.replay = Some(value()?),
+                "--record" => options.record = Some(value()?),
+                "--capture" => options.capture = Some(value()?),
+                "--capture-every" => options.capture_every = Some(positive(&name, &value()?)?),
+                "--capture-format" => {
+                    options.capture_formats = Some(parse_formats(&name, &value()?)?)
+                }
+                "--gif-frames" => options.gif_frames = Some(positive(&name, &value()?)?),
+                _ => return Err(CliError(format!("unknown option `{name}`"))),
+            }
+        }
+        Ok(options)
+    }
+
+    /// scene fields replaced by the command line
+    pub fn overrides(&self) -> Vec<Override> {
+        let mut overrides = Vec::new();
+        if let Some((width, height)) = self.size {
+            overrides.push(Override::new("window", "width", Value::Int(width as i64)));
+            overrides.push(Override::new("window", "height", Value::Int(height as i64)));
+        }
+        if let Some(fps) = self.fps {
+            overrides.push(Override::new("window", "refresh", Value::Int(fps as i64)));
+        }
+        if let Some(count) = self.particles {
+            overrides.push(Override::new(
+                "particles",
+                "count",
+                Value::Int(count as i64),
+            ));
+        }
+        if let Some(radius) = self.radius {
+            overrides.push(Override::new("tortilla", "radius", Value::Float(radius)));
+        }
+        if let Some(seed) = self.seed {
+            overrides.push(Override::new("particles", "seed", Value::Int(seed as i64)));
+        }
+        overrides
+    }
+}
+
+fn number<T: FromStr>(name: &str, value: &str) -> Result<T, CliError> {
+    value
+        .parse()
+        .map_err(|_| CliError(format!("`{name}` expects a number, found `{value}`")))
+}
+
+fn positive<T: FromStr + PartialOrd + Default>(name: &str, value: &str) -> Result<T, CliError> {
+    let number = number(name, value)?;
+    if number > T::default() {
+        Ok(number)
+    } else {
+        Err(CliError(format!(
+            "`{name}` must be positive, found `{value}`"
+        )))
+    }
+}
+
+fn at_most<T: FromStr + PartialOrd + Default + fmt::Display>(
+    name: &str,
+    value: &str,
+    max: T,
+) -> Result<T, CliError> {
+    let number = positive(name, value)?;
+    if number <= max {
+        Ok(number)
+    } else {
+        Err(CliError(format!(
+            "`{name}` must be at most {max}, found `{value}`"
+        )))
+    }
+}
+
+/// `<width>x<height>`
+fn parse_size(name: &str, value: &str) -> Result<(usize, usize), CliError> {
+    let (width, height) = value.split_once('x').ok_or_else(|| {
+        CliError(format!(
+            "`{name}` expects <width>x<height>, found `{value}`"
+        ))
+    })?;
+    Ok((positive(name, width)?, positive(name, height)?))
+}
+
+fn parse_formats(name: &str, value: &str) -> Result<Vec<ImageFormat>, CliError> {
+    match value {
+        "both" => Ok(vec![ImageFormat::Ppm, ImageFormat::Png]),
+        format => ImageFormat::parse(format)
+            .map(|format| vec![format])
+            .ok_or_else(|| {
+                CliError(format!(
+                    "`{name}` expects ppm, png or both, found `{value}`"
+                ))
+            }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, CliError> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn error(args: &[&str]) -> String {
+        parse(args).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn values_follow_as_next_argument_or_after_equals() {
+        for args in [
+            &[
+                "--scene", "a.scene", "--size", "640x480", "--radius", "12.5",
+            ][..],
+            &["--scene=a.scene", "--size=640x480", "--radius=12.5"],
+        ] {
+            let options = parse(args).unwrap();
+            assert_eq!(options.scene.as_deref(), Some("a.scene"));
+            assert_eq!(options.size, Some((640, 480)));
+            assert_eq!(options.radius, Some(12.5));
+        }
+    }
+
+    #[test]
+    fn values_may_contain_equals_and_start_with_a_dash() {
+        let options = parse(&["--record=a=b.rec", "--replay", "-x.rec"]).unwrap();
+        assert_eq!(options.record.as_deref(), Some("a=b.rec"));
+        assert_eq!(options.replay.as_deref(), Some("-x.rec"));
+    }
+
+    #[test]
+    fn flags_and_defaults() {
+        let options = parse(&[]).unwrap();
+        assert!(!options.help && !options.layer_frames);
+        assert!(options.scene.is_none() && options.headless.is_none());
+
+        let options = parse(&["-h", "--layer-frames", "--headless", "0"]).unwrap();
+        assert!(options.help && options.layer_frames);
+        assert_eq!(options.headless, Some(0));
+    }
+
+    #[test]
+    fn capture_formats() {
+        let formats = |value| parse(&["--capture-format", value]).unwrap().capture_formats;
+        assert_eq!(formats("png"), Some(vec![ImageFormat::Png]));
+        assert_eq!(
+            formats("both"),
+            Some(vec![ImageFormat::Ppm, ImageFormat::Png])
+        );
+        assert_eq!(
+            error(&["--capture-format=jpg"]),
+            "`--capture-format` expects ppm, png or both, found `jpg`"
+        );
+    }
+
+    #[test]
+    fn invalid_values_are_errors() {
+        assert_eq!(
+            error(&["--fps", "fast"]),
+            "`--fps` expects a number, found `fast`"
+        );
+        assert_eq!(
+            error(&["--particles=0"]),
+            "`--particles` must be positive, found `0`"
+        );
+        assert_eq!(
+            error(&["--radius", "-3"]),
+            "`--radius` must be positive, found `-3`"
+        );
+        assert_eq!(
+            error(&["--radius", "nan"]),
+            "`--radius` must be positive, found `nan`"
+        );
+        assert_eq!(
+            error(&["--radius=1e9"]),
+            "`--radius` must be at most 200, found `1e9`"
+        );
+        assert_eq!(
+            error(&["--seed", "-1"]),
+            "`--seed` expects a number, found `-1`"
+        );
+        assert_eq!(
+            error(&["--size", "640"]),
+            "`--size` expects <width>x<height>, found `640`"
+        );
+        assert_eq!(
+            error(&["--size=0x480"]),
+            "`--size` must be positive, found `0`"
+        );
+    }
+
+    #[test]
+    fn malformed_command_lines_are_errors() {
+        assert_eq!(error(&["--scene"]), "`--scene` expects a value");
+        assert_eq!(error(&["--wobble=3"]), "unknown option `--wobble`");
+        assert_eq!(error(&["scene.txt"]), "unexpected argument `scene.txt`");
+    }
+
+    #[test]
+    fn overrides_follow_the_options() {
+        let options = parse(&["--size=320x200", "--particles=50", "--seed=7"]).unwrap();
+        let overrides: Vec<_> = options
+            .overrides()
+            .into_iter()
+            .map(|item| (item.table, item.key, item.value))
+            .collect();
+        assert_eq!(
+            overrides,
+            [
+                ("window", "width", Value::Int(320)),
+                ("window", "height", Value::Int(200)),
+                ("particles", "count", Value::Int(50)),
+                ("particles", "seed", Value::Int(7)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,174 @@
+/// 8 bit per channel colour with straight (non premultiplied) alpha
+#[derive(Default, Copy, Clone, Debug, PartialEq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+/// how a colour written to a `Frame` combines with the pixel under it,
+/// every mode but `Replace` is weighted by the source alpha
+#[derive(Default, Copy, Clone, Debug, PartialEq)]
+pub enum BlendMode {
+    Replace,
+    #[default]
+    Alpha,
+    Add,
+    Multiply,
+}
+
+impl Color {
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b, a: 255 }
+    }
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// `0x00RRGGBB` as used by the frame buffer, fully opaque
+    pub const fn from_u32(packed: u32) -> Self {
+        Self::rgb((packed >> 16) as u8, (packed >> 8) as u8, packed as u8)
+    }
+
+    /// `0x00RRGGBB`, alpha is dropped
+    pub const fn to_u32(self) -> u32 {
+        ((self.r as u32) << 16) | ((self.g as u32) << 8) | self.b as u32
+    }
+
+    /// `0xAARRGGBB` as used by transparent frames
+    pub const fn from_argb(packed: u32) -> Self {
+        Self::rgba(
+            (packed >> 16) as u8,
+            (packed >> 8) as u8,
+            packed as u8,
+            (packed >> 24) as u8,
+        )
+    }
+
+    /// `0xAARRGGBB`, alpha is kept
+    pub const fn to_argb(self) -> u32 {
+        ((self.a as u32) << 24) | self.to_u32()
+    }
+
+    pub const fn with_alpha(self, a: u8) -> Self {
+        Self { a, ..self }
+    }
+
+    /// hue in degrees, saturation and value from 0.0 to 1.0
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Self {
+        let hue = hue.rem_euclid(360.0) / 60.0;
+        let chroma = value * saturation;
+        let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+        let (r, g, b) = match hue as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let m = value - chroma;
+        let channel = |c: f32| ((c + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+        Self::rgb(channel(r), channel(g), channel(b))
+    }
+
+    /// (hue in degrees, saturation, value)
+    pub fn to_hsv(self) -> (f32, f32, f32) {
+        let r = self.r as f32 / 255.0;
+        let g = self.g as f32 / 255.0;
+        let b = self.b as f32 / 255.0;
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let chroma = max - min;
+
+        let hue = if chroma == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / chroma).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / chroma + 2.0)
+        } else {
+            60.0 * ((r - g) / chroma + 4.0)
+        };
+        let saturation = if max == 0.0 { 0.0 } else { chroma / max };
+        (hue, saturation, max)
+    }
+
+    /// every channel, alpha included, moves from `self` to `other`
+    pub fn lerp(self, other: Color, t: f32) -> Self {
+        let channel = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        Self {
+            r: channel(self.r, other.r),
+            g: channel(self.g, other.g),
+            b: channel(self.b, other.b),
+            a: channel(self.a, other.a),
+        }
+    }
+
+    /// `self` drawn over `dst`, the result is opaque
+    pub fn blend(self, dst: Color, mode: BlendMode) -> Color {
+        let alpha = self.a as f32 / 255.0;
+        let dst = dst.with_alpha(255);
+        match mode {
+            BlendMode::Replace => self.with_alpha(255),
+            BlendMode::Alpha => dst.lerp(self.with_alpha(255), alpha),
+            BlendMode::Add => {
+                let channel = |d: u8, s: u8| (d as f32 + s as f32 * alpha).min(255.0) as u8;
+                Color::rgb(
+                    channel(dst.r, self.r),
+                    channel(dst.g, self.g),
+                    channel(dst.b, self.b),
+                )
+            }
+            BlendMode::Multiply => {
+                let channel = |d: u8, s: u8| d as f32 * (s as f32 / 255.0);
+                let product = Color::rgb(
+                    channel(dst.r, self.r) as u8,
+                    channel(dst.g, self.g) as u8,
+                    channel(dst.b, self.b) as u8,
+                );
+                dst.lerp(product, alpha)
+            }
+        }
+    }
+
+    /// `self` drawn over a `dst` that may be transparent, the result keeps
+    /// the coverage of both
+    pub fn blend_over(self, dst: Color, mode: BlendMode) -> Color {
+        if mode == BlendMode::Replace || dst.a == 0 {
+            return self;
+        }
+        let src_alpha = self.a as f32 / 255.0;
+        let dst_alpha = dst.a as f32 / 255.0;
+        let alpha = src_alpha + dst_alpha * (1.0 - src_alpha);
+        let color = match mode {
+            BlendMode::Alpha => {
+                let channel = |d: u8, s: u8| {
+                    (s as f32 * src_alpha + d as f32 * dst_alpha * (1.0 - src_alpha)) / alpha
+                };
+                Color::rgb(
+                    channel(dst.r, self.r).round() as u8,
+                    channel(dst.g, self.g).round() as u8,
+                    channel(dst.b, self.b).round() as u8,
+                )
+            }
+            // the opaque result where `dst` is covered, `self` where it is not
+            _ => self.with_alpha(255).lerp(self.blend(dst, mode), dst_alpha),
+        };
+        color.with_alpha((alpha * 255.0).round() as u8)
+    }
+}
+
+impl From<u32> for Color {
+    fn from(packed: u32) -> Self {
+        Color::from_u32(packed)
+    }
+}
+
+impl From<Color> for u32 {
+    fn from(color: Color) -> Self {
+        color.to_u32()
+    }
+}
@@ -1,64 +1,534 @@
-use crate::entity::Entity;
+use crate::action::ActionMap;
+use crate::backend::Backend;
+use crate::camera::{Camera, View};
+use crate::capture::{Capture, GifRecorder};
+use crate::color::{BlendMode, Color};
+use crate::entities::{Entities, EntityId};
+use crate::entity::{Entity, FrameInfo, Layer};
 use crate::frame::Frame;
-use crate::input::Input;
+use crate::input::{Input, InputState};
+use crate::replay::{InputRecorder, InputReplay};
 use crate::rgb;
-use minifb::{Window, WindowOptions};
+use crate::scene::SceneWatcher;
+use crate::snapshot::{Snapshot, SnapshotError};
+use crate::ui::Panel;
+use crate::vector::Vector2D;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+const DEFAULT_MAX_STEPS: usize = 8;
+const DEFAULT_CAPTURE_DIR: &str = "capture";
+const DEFAULT_GIF_FRAMES: usize = 120;
+const DEFAULT_SNAPSHOT_PATH: &str = "snapshot.bin";
+const ZOOM_STEP: f32 = 1.1;
+/// actions that use the wheel themselves, the camera leaves it to them
+const WHEEL_TOOLS: [&str; 2] = ["pinch", "move_anchor"];
+/// actions reported as the current tool, the first held one wins
+const TOOLS: [&str; 3] = ["pinch", "move_anchor", "pan"];
+/// weight of the latest frame in the displayed fps
+const FPS_SMOOTHING: f32 = 0.1;
+
+/// engine level shortcuts handled by `Core`
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Hotkey {
+    TogglePause,
+    ToggleDebug,
+    TogglePanel,
+    ToggleCapture,
+    DumpGif,
+    SaveSnapshot,
+    LoadSnapshot,
+    ToggleLayer(Layer),
+}
+
+const HOTKEYS: [(&str, Hotkey); 11] = [
+    ("pause", Hotkey::TogglePause),
+    ("debug", Hotkey::ToggleDebug),
+    ("panel", Hotkey::TogglePanel),
+    ("capture", Hotkey::ToggleCapture),
+    ("dump_gif", Hotkey::DumpGif),
+    ("save_snapshot", Hotkey::SaveSnapshot),
+    ("load_snapshot", Hotkey::LoadSnapshot),
+    ("layer_background", Hotkey::ToggleLayer(Layer::Background)),
+    ("layer_world", Hotkey::ToggleLayer(Layer::World)),
+    ("layer_overlay", Hotkey::ToggleLayer(Layer::Overlay)),
+    ("layer_ui", Hotkey::ToggleLayer(Layer::Ui)),
+];
 
 pub struct Core {
     frame: Frame,
-    window: Window,
-    refresh: usize,
     input: Input,
-    entities: Vec<Box<dyn Entity>>,
+    entities: Entities,
+    step: f32,
+    max_steps: usize,
+    accumulator: f32,
+    alpha: f32,
+    fps: f32,
+    camera: Camera,
+    paused: bool,
+    debug: bool,
+    panel: Panel,
+    /// indexed by `Layer::index`
+    visible_layers: [bool; Layer::ALL.len()],
+    /// one transparent frame per layer, composited onto `frame` at the end
+    layer_frames: Option<Vec<Frame>>,
+    capture: Capture,
+    gif: GifRecorder,
+    snapshot_path: PathBuf,
+    recorder: Option<InputRecorder>,
+    replay: Option<InputReplay>,
+    scene: Option<SceneWatcher>,
 }
 
 impl Core {
-    pub fn new(title: &str, width: usize, height: usize, refresh: usize) -> Self {
-        let mut window = Window::new(title, width, height, WindowOptions::default())
-            .expect("Frame::new(): Window::new failed");
-
-        window.set_target_fps(refresh);
-
+    pub fn new(width: usize, height: usize, refresh: usize) -> Self {
         Self {
             frame: Frame::new(width, height),
-            window,
-            refresh,
             input: Input::default(),
-            entities: vec![],
+            entities: Entities::default(),
+            step: 1.0 / refresh as f32,
+            max_steps: DEFAULT_MAX_STEPS,
+            accumulator: 0.0,
+            alpha: 0.0,
+            fps: 0.0,
+            camera: Camera::default(),
+            paused: false,
+            debug: false,
+            panel: Panel::default(),
+            visible_layers: [true; Layer::ALL.len()],
+            layer_frames: None,
+            capture: Capture::new(DEFAULT_CAPTURE_DIR),
+            gif: GifRecorder::new(DEFAULT_CAPTURE_DIR, DEFAULT_GIF_FRAMES)
+                .with_every(2)
+                .with_scale(2),
+            snapshot_path: PathBuf::from(DEFAULT_SNAPSHOT_PATH),
+            recorder: None,
+            replay: None,
+            scene: None,
         }
     }
 
-    pub fn is_open(&self) -> bool {
-        self.window.is_open()
+    /// physics rate in Hz, independent of the window refresh rate
+    pub fn with_physics(mut self, hz: usize) -> Self {
+        self.step = 1.0 / hz as f32;
+        self
     }
 
-    pub fn add_entity<E: Entity + 'static>(&mut self, entity: E) {
-        self.entities.push(Box::new(entity))
+    /// max physics steps run in one frame, the remaining time is dropped
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = max_steps;
+        self
     }
 
-    pub fn analyze_event(&mut self) {
-        self.input.refresh(&self.window);
-        for entity in &mut self.entities {
-            entity.handle_input(self.input);
+    /// draw every layer on its own transparent frame before compositing,
+    /// every frame; `layer_frame` then reads back what each layer drew
+    pub fn with_offscreen_layers(mut self) -> Self {
+        let (width, height) = (self.frame.width, self.frame.height);
+        self.layer_frames = Some(
+            Layer::ALL
+                .iter()
+                .map(|_| Frame::transparent(width, height))
+                .collect(),
+        );
+        self
+    }
+
+    pub fn with_capture(mut self, capture: Capture) -> Self {
+        self.capture = capture;
+        self
+    }
+
+    pub fn with_gif_recorder(mut self, gif: GifRecorder) -> Self {
+        self.gif = gif;
+        self
+    }
+
+    /// file written by the save hotkey and read by the restore one
+    pub fn with_snapshot_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.snapshot_path = path.into();
+        self
+    }
+
+    pub fn with_input_recorder(mut self, recorder: InputRecorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// feed recorded input instead of polling the backend, the physics step
+    /// switches to the recorded one so the session plays back identically
+    pub fn with_replay(mut self, replay: InputReplay) -> Self {
+        self.step = replay.step();
+        self.replay = Some(replay);
+        self
+    }
+
+    /// reload the scene file while running
+    pub fn with_scene_watcher(mut self, watcher: SceneWatcher) -> Self {
+        self.scene = Some(watcher);
+        self
+    }
+
+    pub fn with_action_map(mut self, map: ActionMap) -> Self {
+        self.input.set_action_map(map);
+        self
+    }
+
+    /// duration of one physics step in seconds
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    /// added last, so updated and drawn after the others
+    pub fn add_entity<E: Entity + 'static>(&mut self, entity: E) -> EntityId {
+        self.entities.insert(Box::new(entity))
+    }
+
+    /// entity already boxed, as built from a scene
+    pub fn add_boxed_entity(&mut self, entity: Box<dyn Entity>) -> EntityId {
+        self.entities.insert(entity)
+    }
+
+    /// None if `id` is stale, removing it again is harmless
+    pub fn remove_entity(&mut self, id: EntityId) -> Option<Box<dyn Entity>> {
+        self.entities.remove(id)
+    }
+
+    /// None if `id` is stale or the entity is not a `T`
+    pub fn get_entity<T: Entity>(&self, id: EntityId) -> Option<&T> {
+        self.entities.get(id)?.as_any().downcast_ref()
+    }
+
+    pub fn get_entity_mut<T: Entity>(&mut self, id: EntityId) -> Option<&mut T> {
+        self.entities.get_mut(id)?.as_any_mut().downcast_mut()
+    }
+
+    /// false if `id` is stale
+    pub fn set_entity_layer(&mut self, id: EntityId, layer: Layer) -> bool {
+        self.entities.set_layer(id, layer)
+    }
+
+    pub fn layer_visible(&self, layer: Layer) -> bool {
+        self.visible_layers[layer.index()]
+    }
+
+    pub fn set_layer_visible(&mut self, layer: Layer, visible: bool) {
+        self.visible_layers[layer.index()] = visible;
+    }
+
+    /// offscreen frame of `layer` as drawn this frame, if enabled
+    pub fn layer_frame(&self, layer: Layer) -> Option<&Frame> {
+        Some(&self.layer_frames.as_ref()?[layer.index()])
+    }
+
+    pub fn entities(&self) -> &Entities {
+        &self.entities
+    }
+
+    pub fn entities_mut(&mut self) -> &mut Entities {
+        &mut self.entities
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            records: self.entities.iter().map(|entity| entity.save()).collect(),
+        }
+    }
+
+    /// entities must match the snapshot records one to one, in order;
+    /// nothing changes unless every record decodes
+    pub fn restore(&mut self, mut snapshot: Snapshot) -> Result<(), SnapshotError> {
+        if snapshot.records.len() != self.entities.len() {
+            return Err(SnapshotError::Format(format!(
+                "snapshot has {} entities, core has {}",
+                snapshot.records.len(),
+                self.entities.len()
+            )));
+        }
+        // decode everything first so a bad record leaves every entity as is
+        let restores = self
+            .entities
+            .iter()
+            .zip(&mut snapshot.records)
+            .map(|(entity, record)| entity.restore(record))
+            .collect::<Result<Vec<_>, _>>()?;
+        for (entity, restore) in self.entities.iter_mut().zip(restores) {
+            restore(entity.as_any_mut());
+        }
+        self.accumulator = 0.0;
+        self.alpha = 0.0;
+        Ok(())
+    }
+
+    pub fn save_snapshot(&self, path: &Path) -> Result<(), SnapshotError> {
+        self.snapshot().save(path)
+    }
+
+    pub fn load_snapshot(&mut self, path: &Path) -> Result<(), SnapshotError> {
+        self.restore(Snapshot::load(path)?)
+    }
+
+    pub fn run(&mut self, backend: &mut dyn Backend) {
+        let mut last = Instant::now();
+        while backend.is_open() {
+            let now = Instant::now();
+            let dt = backend
+                .frame_time()
+                .unwrap_or_else(|| now.duration_since(last).as_secs_f32());
+            last = now;
+
+            self.resize(backend.size());
+            let (dt, state) = self.next_input(backend, dt);
+            self.analyze_event(&state, dt);
+            for (action, hotkey) in HOTKEYS {
+                if self.input.action(action).pressed {
+                    self.handle_hotkey(hotkey);
+                }
+            }
+            if let Some(scene) = &mut self.scene {
+                scene.poll(dt, &mut self.entities);
+            }
+            self.update(dt);
+            self.observe(dt);
+            self.draw();
+            self.capture_frame(dt);
+            backend.present(&self.frame);
+        }
+
+        if let Some(recorder) = &mut self.recorder
+            && let Err(err) = recorder.flush()
+        {
+            eprintln!("input recording: {err}");
+        }
+    }
+
+    /// input and dt of this frame, from the replay while it lasts
+    fn next_input(&mut self, backend: &mut dyn Backend, dt: f32) -> (f32, InputState) {
+        let frame = self.replay.as_mut().and_then(|replay| replay.next_frame());
+        let (dt, state) = match frame {
+            Some(frame) => frame,
+            None => {
+                if self.replay.take().is_some() {
+                    eprintln!("replay: finished");
+                }
+                (dt, backend.poll_input())
+            }
+        };
+
+        if let Some(recorder) = &mut self.recorder
+            && let Err(err) = recorder.record(dt, &state)
+        {
+            eprintln!("input recording failed, stopping: {err}");
+            self.recorder = None;
+        }
+        (dt, state)
+    }
+
+    fn handle_hotkey(&mut self, hotkey: Hotkey) {
+        match hotkey {
+            Hotkey::TogglePause => self.paused = !self.paused,
+            Hotkey::ToggleDebug => self.debug = !self.debug,
+            Hotkey::TogglePanel => self.panel.toggle(),
+            Hotkey::ToggleCapture => {
+                self.capture.toggle();
+                eprintln!(
+                    "capture: {}",
+                    if self.capture.is_enabled() {
+                        "on"
+                    } else {
+                        "off"
+                    }
+                );
+            }
+            Hotkey::DumpGif => match self.gif.dump() {
+                Ok(path) => eprintln!("gif: saved {}", path.display()),
+                Err(err) => eprintln!("gif: {err}"),
+            },
+            Hotkey::SaveSnapshot => {
+                // keep a readable copy next to the binary one
+                let text = self.snapshot_path.with_extension("txt");
+                for path in [self.snapshot_path.clone(), text] {
+                    match self.save_snapshot(&path) {
+                        Ok(()) => eprintln!("snapshot: saved {}", path.display()),
+                        Err(err) => eprintln!("snapshot: {err}"),
+                    }
+                }
+            }
+            Hotkey::LoadSnapshot => {
+                let path = self.snapshot_path.clone();
+                match self.load_snapshot(&path) {
+                    Ok(()) => eprintln!("snapshot: restored {}", path.display()),
+                    Err(err) => eprintln!("snapshot: {err}"),
+                }
+            }
+            Hotkey::ToggleLayer(layer) => {
+                let visible = !self.layer_visible(layer);
+                self.set_layer_visible(layer, visible);
+                eprintln!(
+                    "layer {}: {}",
+                    layer.name(),
+                    if visible { "shown" } else { "hidden" }
+                );
+            }
+        }
+    }
+
+    fn capture_frame(&mut self, dt: f32) {
+        self.gif.push(&self.frame, dt);
+        if let Err(err) = self.capture.record(&self.frame) {
+            eprintln!("capture failed, stopping: {err}");
+            self.capture.toggle();
+        }
+    }
+
+    fn resize(&mut self, size: Vector2D<usize>) {
+        if size.x != self.frame.width || size.y != self.frame.height {
+            self.frame = Frame::new(size.x, size.y);
+            for frame in self.layer_frames.iter_mut().flatten() {
+                *frame = Frame::transparent(size.x, size.y);
+            }
+        }
+    }
+
+    fn move_camera(&mut self) {
+        let mouse = self.input.mouse;
+        if self.input.action("pan").held {
+            self.camera.pan(mouse.delta);
+        }
+
+        let mut zoom = 0.0;
+        if !WHEEL_TOOLS.iter().any(|tool| self.input.action(tool).held) {
+            zoom += mouse.wheel.y;
+        }
+        if self.input.action("zoom_in").pressed {
+            zoom += 1.0;
+        }
+        if self.input.action("zoom_out").pressed {
+            zoom -= 1.0;
+        }
+        if zoom != 0.0 {
+            self.camera.zoom_at(mouse.pos, ZOOM_STEP.powf(zoom));
+        }
+
+        if self.input.action("reset_view").pressed {
+            self.camera = Camera::default();
+        }
+    }
+
+    pub fn analyze_event(&mut self, state: &InputState, dt: f32) {
+        // the panel keeps the mouse buttons to itself while it is in use,
+        // it is out of reach while the ui layer is hidden
+        let width = self.frame.width;
+        let captured = self.layer_visible(Layer::Ui) && {
+            self.panel.interact(state, width, &mut self.entities);
+            self.panel.captures()
+        };
+        let state = if captured {
+            state.without_mouse_buttons()
+        } else {
+            *state
+        };
+
+        self.input.refresh(&state, dt);
+        self.move_camera();
+        self.input.project(&self.camera);
+        for entity in self.entities.iter_mut() {
+            entity.handle_input(&self.input);
         }
     }
 
     pub fn update(&mut self, dt: f32) {
-        for entity in &mut self.entities {
-            entity.update(dt);
+        if self.paused {
+            return;
+        }
+        self.accumulator += dt;
+
+        let mut steps = 0;
+        while self.accumulator >= self.step {
+            if steps >= self.max_steps {
+                // too far behind, drop the backlog instead of spiraling
+                self.accumulator %= self.step;
+                break;
+            }
+            self.entities.update(self.step);
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+
+        self.alpha = self.accumulator / self.step;
+    }
+
+    /// hand the frame stats to every entity
+    pub fn observe(&mut self, dt: f32) {
+        if dt > 0.0 {
+            let fps = 1.0 / dt;
+            self.fps = if self.fps == 0.0 {
+                fps
+            } else {
+                self.fps + (fps - self.fps) * FPS_SMOOTHING
+            };
+        }
+        let info = FrameInfo {
+            fps: self.fps,
+            dt,
+            paused: self.paused,
+            tool: TOOLS
+                .into_iter()
+                .find(|tool| self.input.action(tool).held)
+                .unwrap_or("none"),
+            entities: self
+                .entities
+                .iter()
+                .map(|entity| (entity.name(), entity.inspect()))
+                .collect(),
+        };
+        for entity in self.entities.iter_mut() {
+            entity.observe(&info);
         }
     }
 
     pub fn draw(&mut self) {
         self.frame.fill(rgb!(0, 0, 0));
-        for entity in &self.entities {
-            entity.draw(&mut self.frame);
+        let mut frame = std::mem::take(&mut self.frame);
+        let mut layer_frames = self.layer_frames.take();
+        for layer in Layer::ALL {
+            if !self.layer_visible(layer) {
+                continue;
+            }
+            match &mut layer_frames {
+                Some(frames) => {
+                    let target = &mut frames[layer.index()];
+                    target.fill(Color::rgba(0, 0, 0, 0));
+                    self.draw_layer(target, layer);
+                    frame.set_blend_mode(BlendMode::default());
+                    frame.draw_frame(target);
+                }
+                None => self.draw_layer(&mut frame, layer),
+            }
         }
+        self.frame = frame;
+        self.layer_frames = layer_frames;
     }
 
-    pub fn next_frame(&mut self) {
-        self.window
-            .update_with_buffer(&self.frame.buffer, self.frame.width, self.frame.height)
-            .expect("Failed to update buffer");
+    fn draw_layer(&mut self, frame: &mut Frame, layer: Layer) {
+        let view = View {
+            camera: self.camera,
+            alpha: self.alpha,
+        };
+        if layer == Layer::Overlay && self.debug {
+            // under the overlay entities but over every world one
+            for entity in self.entities.iter() {
+                frame.set_blend_mode(BlendMode::default());
+                entity.draw_debug(frame, &view);
+            }
+        }
+        for entity in self.entities.iter_layer(layer) {
+            // entities start from the default mode whatever the previous one left
+            frame.set_blend_mode(BlendMode::default());
+            entity.draw(frame, &view);
+        }
+        if layer == Layer::Ui {
+            frame.set_blend_mode(BlendMode::default());
+            self.panel.draw(frame, &mut self.entities);
+        }
     }
 }
@@ -0,0 +1,58 @@
+use crate::camera::View;
+use crate::color::Color;
+use crate::frame::Frame;
+use crate::spatial_grid::SpatialGrid;
+use crate::vector::Vector2D;
+use std::collections::HashSet;
+
+const TILE_FILL: Color = Color::rgba(80, 160, 255, 50);
+const TILE_BORDER: Color = Color::rgba(80, 160, 255, 140);
+/// tiles smaller than this many pixels are filled without a border
+const MIN_BORDERED_TILE: f32 = 4.0;
+/// strain at which a link is fully red (stretched) or blue (compressed)
+const FULL_STRAIN: f32 = 0.5;
+
+/// green at rest length, towards red when stretched and blue when compressed
+pub fn strain_color(length: f32, rest_length: f32) -> Color {
+    let strain = (length / rest_length - 1.0).clamp(-FULL_STRAIN, FULL_STRAIN);
+    Color::from_hsv(120.0 - strain / FULL_STRAIN * 120.0, 1.0, 1.0)
+}
+
+/// highlight the tiles of `grid` holding at least one of `positions`
+pub fn draw_tiles(
+    frame: &mut Frame,
+    view: &View,
+    grid: &SpatialGrid,
+    positions: impl Iterator<Item = Vector2D<f32>>,
+) {
+    let occupied: HashSet<(isize, isize)> = positions
+        .map(|pos| grid.pos_to_tile(pos))
+        .map(|tile| (tile.x, tile.y))
+        .collect();
+
+    let tile_size = grid.tile_size();
+    let size = (tile_size * view.camera.zoom).max(1.0);
+    for (x, y) in occupied {
+        let corner = Vector2D::new(x as f32 * tile_size, y as f32 * tile_size);
+        let corner = view.camera.world_to_screen(corner);
+        frame.fill_rect(corner, Vector2D::new(size, size), TILE_FILL);
+        if size >= MIN_BORDERED_TILE {
+            frame.rect(corner, Vector2D::new(size, size), TILE_BORDER);
+        }
+    }
+}
+
+/// crosshair with a ring, for anchors and grabbed cells
+pub fn draw_marker(frame: &mut Frame, pos: Vector2D<f32>, radius: f32, color: Color) {
+    frame.circle(pos, radius, color);
+    frame.line(
+        Vector2D::new(pos.x - radius * 1.5, pos.y),
+        Vector2D::new(pos.x + radius * 1.5, pos.y),
+        color,
+    );
+    frame.line(
+        Vector2D::new(pos.x, pos.y - radius * 1.5),
+        Vector2D::new(pos.x, pos.y + radius * 1.5),
+        color,
+    );
+}
@@ -0,0 +1,233 @@
+use crate::vector::Vector2D;
+
+struct Triangle {
+    /// counter clockwise
+    vertices: [usize; 3],
+    /// triangle across edge `i`, which goes from vertex `i` to the next
+    neighbors: [Option<usize>; 3],
+    center: Vector2D<f64>,
+    radius_sq: f64,
+    alive: bool,
+}
+
+impl Triangle {
+    fn new(vertices: [usize; 3], points: &[Vector2D<f64>]) -> Self {
+        let [a, b, c] = vertices.map(|i| points[i]);
+        let d = 2.0 * (a.x * (b.y - c.y) + b.x * (c.y - a.y) + c.x * (a.y - b.y));
+        let (a_sq, b_sq, c_sq) = (
+            a.x * a.x + a.y * a.y,
+            b.x * b.x + b.y * b.y,
+            c.x * c.x + c.y * c.y,
+        );
+        let center = Vector2D {
+            x: (a_sq * (b.y - c.y) + b_sq * (c.y - a.y) + c_sq * (a.y - b.y)) / d,
+            y: (a_sq * (c.x - b.x) + b_sq * (a.x - c.x) + c_sq * (b.x - a.x)) / d,
+        };
+        let radius_sq = (a.x - center.x).powi(2) + (a.y - center.y).powi(2);
+        Self {
+            vertices,
+            neighbors: [None; 3],
+            center,
+            radius_sq,
+            alive: true,
+        }
+    }
+
+    /// points on the circle are outside, co-circular grids keep their
+    /// existing triangles
+    fn circumcircle_contains(&self, p: Vector2D<f64>) -> bool {
+        let dist_sq = (p.x - self.center.x).powi(2) + (p.y - self.center.y).powi(2);
+        dist_sq < self.radius_sq * (1.0 - 1e-9)
+    }
+
+    fn edge(&self, i: usize) -> (usize, usize) {
+        (self.vertices[i], self.vertices[(i + 1) % 3])
+    }
+}
+
+/// positive when `a`, `b`, `c` turn counter clockwise
+fn orient(a: Vector2D<f64>, b: Vector2D<f64>, c: Vector2D<f64>) -> f64 {
+    (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+}
+
+struct Triangulation {
+    points: Vec<Vector2D<f64>>,
+    triangles: Vec<Triangle>,
+    /// slots of removed triangles, reused by the next ones
+    free: Vec<usize>,
+    /// last triangle created, walks start from there
+    last: usize,
+    // buffers kept between insertions
+    stack: Vec<usize>,
+    boundary: Vec<(usize, usize, Option<usize>)>,
+    created: Vec<(usize, usize, usize)>,
+}
+
+impl Triangulation {
+    fn add(&mut self, triangle: Triangle) -> usize {
+        match self.free.pop() {
+            Some(slot) => {
+                self.triangles[slot] = triangle;
+                slot
+            }
+            None => {
+                self.triangles.push(triangle);
+                self.triangles.len() - 1
+            }
+        }
+    }
+
+    /// triangle holding `p`, walking across the edges `p` lies beyond
+    fn locate(&self, p: Vector2D<f64>) -> usize {
+        let mut current = self.last;
+        'walk: for _ in 0..self.triangles.len() {
+            let triangle = &self.triangles[current];
+            for i in 0..3 {
+                let (a, b) = triangle.edge(i);
+                if orient(self.points[a], self.points[b], p) < 0.0
+                    && let Some(next) = triangle.neighbors[i]
+                {
+                    current = next;
+                    continue 'walk;
+                }
+            }
+            return current;
+        }
+        // rounding sent the walk in circles
+        (0..self.triangles.len())
+            .filter(|&t| self.triangles[t].alive)
+            .find(|&t| {
+                let triangle = &self.triangles[t];
+                (0..3).all(|i| {
+                    let (a, b) = triangle.edge(i);
+                    orient(self.points[a], self.points[b], p) >= 0.0
+                })
+            })
+            .unwrap_or(self.last)
+    }
+
+    /// remove the triangles whose circumcircle holds point `i`, they touch
+    /// each other, and fan the hole they leave around `i`
+    fn insert(&mut self, i: usize) {
+        let p = self.points[i];
+        let start = self.locate(p);
+        if !self.triangles[start].circumcircle_contains(p) {
+            // lands on an existing point
+            return;
+        }
+
+        let mut stack = std::mem::take(&mut self.stack);
+        let mut boundary = std::mem::take(&mut self.boundary);
+        let mut created = std::mem::take(&mut self.created);
+        boundary.clear();
+        created.clear();
+
+        self.triangles[start].alive = false;
+        stack.push(start);
+        while let Some(t) = stack.pop() {
+            self.free.push(t);
+            for edge in 0..3 {
+                let (a, b) = self.triangles[t].edge(edge);
+                match self.triangles[t].neighbors[edge] {
+                    Some(n) if !self.triangles[n].alive => {}
+                    Some(n) if self.triangles[n].circumcircle_contains(p) => {
+                        self.triangles[n].alive = false;
+                        stack.push(n);
+                    }
+                    outer => boundary.push((a, b, outer)),
+                }
+            }
+        }
+
+        for &(a, b, outer) in &boundary {
+            let mut triangle = Triangle::new([a, b, i], &self.points);
+            triangle.neighbors[0] = outer;
+            let t = self.add(triangle);
+            if let Some(outer) = outer {
+                let back = &mut self.triangles[outer];
+                if let Some(edge) = (0..3).find(|&edge| back.edge(edge) == (b, a)) {
+                    back.neighbors[edge] = Some(t);
+                }
+            }
+            created.push((a, b, t));
+        }
+        for &(a, b, t) in &created {
+            let next = created.iter().find(|other| other.0 == b);
+            let prev = created.iter().find(|other| other.1 == a);
+            self.triangles[t].neighbors[1] = next.map(|other| other.2);
+            self.triangles[t].neighbors[2] = prev.map(|other| other.2);
+            self.last = t;
+        }
+
+        self.stack = stack;
+        self.boundary = boundary;
+        self.created = created;
+    }
+}
+
+/// bowyer-watson delaunay triangulation, triangles index into `points`
+pub fn triangulate(points: &[Vector2D<f32>]) -> Vec<[usize; 3]> {
+    if points.len() < 3 {
+        return Vec::new();
+    }
+    let mut all: Vec<Vector2D<f64>> = points
+        .iter()
+        .map(|p| Vector2D {
+            x: p.x as f64,
+            y: p.y as f64,
+        })
+        .collect();
+
+    // super triangle enclosing every point, removed at the end
+    let (mut min, mut max) = (all[0], all[0]);
+    for p in &all {
+        min = Vector2D {
+            x: min.x.min(p.x),
+            y: min.y.min(p.y),
+        };
+        max = Vector2D {
+            x: max.x.max(p.x),
+            y: max.y.max(p.y),
+        };
+    }
+    let span = (max.x - min.x).max(max.y - min.y).max(1.0) * 20.0;
+    let mid = Vector2D {
+        x: (min.x + max.x) / 2.0,
+        y: (min.y + max.y) / 2.0,
+    };
+    let first_super = all.len();
+    all.push(Vector2D {
+        x: mid.x - span,
+        y: mid.y - span,
+    });
+    all.push(Vector2D {
+        x: mid.x + span,
+        y: mid.y - span,
+    });
+    all.push(Vector2D {
+        x: mid.x,
+        y: mid.y + span,
+    });
+
+    let super_triangle = Triangle::new([first_super, first_super + 1, first_super + 2], &all);
+    let mut triangulation = Triangulation {
+        points: all,
+        triangles: vec![super_triangle],
+        free: Vec::new(),
+        last: 0,
+        stack: Vec::new(),
+        boundary: Vec::new(),
+        created: Vec::new(),
+    };
+    for i in 0..points.len() {
+        triangulation.insert(i);
+    }
+
+    triangulation
+        .triangles
+        .into_iter()
+        .filter(|triangle| triangle.alive)
+        .map(|triangle| triangle.vertices)
+        .filter(|vertices| vertices.iter().all(|&v| v < first_super))
+        .collect()
+}
@@ -0,0 +1,265 @@
+use crate::entity::{Entity, Layer};
+
+/// handle on an entity of `Core`, stale once the entity is removed even
+/// if its slot is reused
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EntityId {
+    index: u32,
+    generation: u32,
+}
+
+#[derive(Default)]
+struct Slot {
+    generation: u32,
+    /// index in `Entities::items`, `None` while free or reserved by a spawn
+    /// not applied yet
+    position: Option<usize>,
+}
+
+/// handles given out so far, shared by `Entities` and `Commands`
+#[derive(Default)]
+struct Slots {
+    slots: Vec<Slot>,
+    free: Vec<u32>,
+}
+
+impl Slots {
+    fn allocate(&mut self) -> EntityId {
+        match self.free.pop() {
+            Some(index) => EntityId {
+                index,
+                generation: self.slots[index as usize].generation,
+            },
+            None => {
+                self.slots.push(Slot::default());
+                EntityId {
+                    index: self.slots.len() as u32 - 1,
+                    generation: 0,
+                }
+            }
+        }
+    }
+
+    fn position(&self, id: EntityId) -> Option<usize> {
+        let slot = self.slots.get(id.index as usize)?;
+        if slot.generation == id.generation {
+            slot.position
+        } else {
+            None
+        }
+    }
+
+    fn release(&mut self, id: EntityId) {
+        let slot = &mut self.slots[id.index as usize];
+        slot.generation += 1;
+        slot.position = None;
+        self.free.push(id.index);
+    }
+}
+
+/// entities in update and draw order, each with its handle
+///
+/// removed entities leave a hole, filled in one go once holes make up
+/// half of the list, so handles resolve without a search
+#[derive(Default)]
+pub struct Entities {
+    items: Vec<Option<Box<dyn Entity>>>,
+    /// handle of `items[i]`
+    ids: Vec<EntityId>,
+    /// layer `items[i]` is drawn on
+    layers: Vec<Layer>,
+    holes: usize,
+    slots: Slots,
+    queue: Vec<Command>,
+}
+
+impl Entities {
+    fn insert_at(&mut self, id: EntityId, entity: Box<dyn Entity>) {
+        self.slots.slots[id.index as usize].position = Some(self.items.len());
+        self.layers.push(entity.layer());
+        self.items.push(Some(entity));
+        self.ids.push(id);
+    }
+
+    /// appended last, so drawn over the others of its layer
+    pub fn insert(&mut self, entity: Box<dyn Entity>) -> EntityId {
+        let id = self.slots.allocate();
+        self.insert_at(id, entity);
+        id
+    }
+
+    /// the others keep their order, `id` and its copies go stale
+    pub fn remove(&mut self, id: EntityId) -> Option<Box<dyn Entity>> {
+        let position = self.slots.position(id)?;
+        let entity = self.items[position].take();
+        self.slots.release(id);
+        self.holes += 1;
+        if self.holes * 2 > self.items.len() {
+            self.compact();
+        }
+        entity
+    }
+
+    /// drop the holes, keeping the order
+    fn compact(&mut self) {
+        let mut kept = 0;
+        for position in 0..self.items.len() {
+            if self.items[position].is_none() {
+                continue;
+            }
+            self.items.swap(kept, position);
+            self.ids.swap(kept, position);
+            self.layers.swap(kept, position);
+            self.slots.slots[self.ids[kept].index as usize].position = Some(kept);
+            kept += 1;
+        }
+        self.items.truncate(kept);
+        self.ids.truncate(kept);
+        self.layers.truncate(kept);
+        self.holes = 0;
+    }
+
+    /// swap the entity behind `id` in place, the handle and its layer stay
+    pub fn replace(&mut self, id: EntityId, entity: Box<dyn Entity>) -> Option<Box<dyn Entity>> {
+        let position = self.slots.position(id)?;
+        self.items[position].replace(entity)
+    }
+
+    pub fn layer(&self, id: EntityId) -> Option<Layer> {
+        let position = self.slots.position(id)?;
+        Some(self.layers[position])
+    }
+
+    /// move the entity to another layer, false if `id` is stale
+    pub fn set_layer(&mut self, id: EntityId, layer: Layer) -> bool {
+        match self.slots.position(id) {
+            Some(position) => {
+                self.layers[position] = layer;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn contains(&self, id: EntityId) -> bool {
+        self.slots.position(id).is_some()
+    }
+
+    pub fn get(&self, id: EntityId) -> Option<&dyn Entity> {
+        let position = self.slots.position(id)?;
+        self.items[position].as_deref()
+    }
+
+    pub fn get_mut(&mut self, id: EntityId) -> Option<&mut dyn Entity> {
+        let position = self.slots.position(id)?;
+        self.items[position].as_deref_mut()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len() - self.holes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// handles in order
+    pub fn ids(&self) -> impl Iterator<Item = EntityId> {
+        self.ids
+            .iter()
+            .zip(&self.items)
+            .filter(|(_, entity)| entity.is_some())
+            .map(|(&id, _)| id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Entity> {
+        self.items.iter().flatten().map(|entity| entity.as_ref())
+    }
+
+    /// entities of one layer, in order
+    pub fn iter_layer(&self, layer: Layer) -> impl Iterator<Item = &dyn Entity> {
+        self.layers
+            .iter()
+            .zip(&self.items)
+            .filter(move |&(&other, _)| other == layer)
+            .filter_map(|(_, entity)| entity.as_deref())
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut dyn Entity> {
+        self.items
+            .iter_mut()
+            .flatten()
+            .map(|entity| entity.as_mut())
+    }
+
+    /// like `iter_mut`, with the handle of each entity
+    pub fn iter_mut_with_ids(&mut self) -> impl Iterator<Item = (EntityId, &mut dyn Entity)> {
+        self.ids
+            .iter()
+            .copied()
+            .zip(&mut self.items)
+            .filter_map(|(id, entity)| Some((id, entity.as_mut()?.as_mut())))
+    }
+
+    /// step every entity, then apply what they queued in their `Commands`
+    pub fn update(&mut self, dt: f32) {
+        for (&id, entity) in self.ids.iter().zip(&mut self.items) {
+            if let Some(entity) = entity {
+                let mut commands = Commands {
+                    current: id,
+                    queue: &mut self.queue,
+                    slots: &mut self.slots,
+                };
+                entity.update(dt, &mut commands);
+            }
+        }
+
+        let mut queue = std::mem::take(&mut self.queue);
+        for command in queue.drain(..) {
+            match command {
+                Command::Spawn(id, entity) => self.insert_at(id, entity),
+                Command::Despawn(id) => {
+                    self.remove(id);
+                }
+            }
+        }
+        self.queue = queue;
+    }
+}
+
+enum Command {
+    Spawn(EntityId, Box<dyn Entity>),
+    Despawn(EntityId),
+}
+
+/// changes to the entity list asked for by one entity during `update`,
+/// applied in order once every entity has stepped
+pub struct Commands<'a> {
+    current: EntityId,
+    queue: &'a mut Vec<Command>,
+    slots: &'a mut Slots,
+}
+
+impl Commands<'_> {
+    /// the entity being updated
+    pub fn current(&self) -> EntityId {
+        self.current
+    }
+
+    /// added after the current step, it first updates on the next one;
+    /// the handle is valid right away but `Entities` only knows it then
+    pub fn spawn<E: Entity + 'static>(&mut self, entity: E) -> EntityId {
+        self.spawn_boxed(Box::new(entity))
+    }
+
+    pub fn spawn_boxed(&mut self, entity: Box<dyn Entity>) -> EntityId {
+        let id = self.slots.allocate();
+        self.queue.push(Command::Spawn(id, entity));
+        id
+    }
+
+    /// removed after the current step, a stale `id` is ignored
+    pub fn despawn(&mut self, id: EntityId) {
+        self.queue.push(Command::Despawn(id));
+    }
+}
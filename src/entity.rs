@@ -1,16 +1,133 @@
+use crate::camera::View;
+use crate::entities::Commands;
 use crate::frame::Frame;
 use crate::input::Input;
+use crate::param::Param;
+use crate::snapshot::{Record, SnapshotError};
+use std::any::Any;
 
 pub trait Inputable {
-    fn handle_input(&mut self, input: Input);
+    fn handle_input(&mut self, input: &Input);
 }
 
 pub trait Updatable {
-    fn update(&mut self, dt: f32);
+    /// one physics step, entities to add or remove go through `commands`
+    fn update(&mut self, dt: f32, commands: &mut Commands);
 }
 
 pub trait Drawable {
-    fn draw(&self, frame: &mut Frame);
+    fn draw(&self, frame: &mut Frame, view: &View);
+
+    /// solver internals drawn over every entity when the overlay is on
+    fn draw_debug(&self, _frame: &mut Frame, _view: &View) {}
+}
+
+pub trait Snapshotable {
+    fn save(&self) -> Record;
+
+    /// decode the state written by `save` without touching `self`, the
+    /// record is read in order; `Core` applies every decoded state only
+    /// once all the records of a snapshot decoded
+    fn restore(&self, record: &mut Record) -> Result<Restore, SnapshotError>;
+}
+
+/// decoded state, applied to the entity that decoded it
+pub type Restore = Box<dyn FnOnce(&mut dyn Any)>;
+
+/// `Restore` writing to an entity of type `E`
+pub fn restore_with<E: Any>(apply: impl FnOnce(&mut E) + 'static) -> Restore {
+    Box::new(move |entity: &mut dyn Any| {
+        apply(
+            entity
+                .downcast_mut()
+                .expect("state applied to another kind of entity"),
+        )
+    })
 }
 
-pub trait Entity: Inputable + Updatable + Drawable {}
+/// what the core measured this frame, handed to every entity before drawing
+#[derive(Default, Clone, Debug)]
+pub struct FrameInfo {
+    pub fps: f32,
+    pub dt: f32,
+    pub paused: bool,
+    /// action currently driving the mouse, `none` when idle
+    pub tool: &'static str,
+    /// `name` and `inspect` of every entity, in order
+    pub entities: Vec<(&'static str, Vec<(&'static str, String)>)>,
+}
+
+/// drawing pass of an entity, layers are drawn in this order and each one
+/// can be hidden
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Layer {
+    Background,
+    World,
+    Overlay,
+    Ui,
+}
+
+impl Layer {
+    pub const ALL: [Layer; 4] = [Layer::Background, Layer::World, Layer::Overlay, Layer::Ui];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Layer::Background => "background",
+            Layer::World => "world",
+            Layer::Overlay => "overlay",
+            Layer::Ui => "ui",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Layer::ALL
+            .into_iter()
+            .find(|layer| layer.name().eq_ignore_ascii_case(name))
+    }
+
+    /// position in `ALL`
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// lets `Core` hand back an entity as its concrete type
+pub trait AsAny: Any {
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Any> AsAny for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+pub trait Entity: Inputable + Updatable + Drawable + Snapshotable + AsAny {
+    fn name(&self) -> &'static str {
+        "entity"
+    }
+
+    /// layer the entity is drawn on unless `Core` is told otherwise
+    fn layer(&self) -> Layer {
+        Layer::World
+    }
+
+    /// labelled values describing the current state, shown by the hud
+    fn inspect(&self) -> Vec<(&'static str, String)> {
+        Vec::new()
+    }
+
+    /// called once per frame, before drawing
+    fn observe(&mut self, _info: &FrameInfo) {}
+
+    /// values the parameter panel lets the user edit live
+    fn params_mut(&mut self) -> Vec<Param<'_>> {
+        Vec::new()
+    }
+}
@@ -1,8 +1,15 @@
+use crate::color::{BlendMode, Color};
+use crate::font;
+use crate::vector::Vector2D;
+
 #[derive(Default)]
 pub struct Frame {
     pub width: usize,
     pub height: usize,
     pub buffer: Vec<u32>,
+    blend: BlendMode,
+    /// pixels are `0xAARRGGBB` and start fully transparent
+    transparent: bool,
 }
 
 impl Frame {
@@ -11,12 +18,352 @@ impl Frame {
             width,
             height,
             buffer: vec![0u32; width * height],
+            blend: BlendMode::default(),
+            transparent: false,
+        }
+    }
+
+    /// keeps the alpha of what is drawn, to be composited with `draw_frame`
+    pub fn transparent(width: usize, height: usize) -> Self {
+        Self {
+            transparent: true,
+            ..Self::new(width, height)
+        }
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        self.transparent
+    }
+
+    fn pack(&self, color: Color) -> u32 {
+        if self.transparent {
+            color.to_argb()
+        } else {
+            color.to_u32()
         }
     }
 
-    pub fn fill(&mut self, color: u32) {
+    fn unpack(&self, pixel: u32) -> Color {
+        if self.transparent {
+            Color::from_argb(pixel)
+        } else {
+            Color::from_u32(pixel)
+        }
+    }
+
+    /// overwrite every pixel, ignoring the blend mode
+    pub fn fill(&mut self, color: impl Into<Color>) {
+        let color = self.pack(color.into());
         for pixel in &mut self.buffer {
             *pixel = color;
         }
     }
+
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend
+    }
+
+    /// how the following writes combine with the pixels already drawn
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.blend = mode;
+    }
+
+    fn index(&self, x: isize, y: isize) -> Option<usize> {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            None
+        } else {
+            Some(y as usize * self.width + x as usize)
+        }
+    }
+
+    fn put(&mut self, index: usize, color: Color) {
+        let color = if color.a == 255 && self.blend == BlendMode::Alpha {
+            color
+        } else if self.transparent {
+            color.blend_over(self.unpack(self.buffer[index]), self.blend)
+        } else {
+            color.blend(self.unpack(self.buffer[index]), self.blend)
+        };
+        self.buffer[index] = self.pack(color);
+    }
+
+    /// `other` drawn over this frame with the current blend mode, both
+    /// must have the same size
+    pub fn draw_frame(&mut self, other: &Frame) {
+        debug_assert_eq!(self.buffer.len(), other.buffer.len());
+        for (index, &pixel) in other.buffer.iter().enumerate() {
+            let color = other.unpack(pixel);
+            if color.a > 0 {
+                self.put(index, color);
+            }
+        }
+    }
+
+    /// blended with the current mode, pixels outside of the frame are ignored
+    pub fn set_pixel(&mut self, x: isize, y: isize, color: impl Into<Color>) {
+        if let Some(index) = self.index(x, y) {
+            self.put(index, color.into());
+        }
+    }
+
+    /// `color` with its alpha scaled by `coverage`, from 0.0 to 1.0
+    fn cover_pixel(&mut self, x: isize, y: isize, color: Color, coverage: f32) {
+        let alpha = color.a as f32 * coverage.clamp(0.0, 1.0);
+        self.set_pixel(x, y, color.with_alpha(alpha as u8));
+    }
+
+    fn span(&mut self, y: isize, x0: isize, x1: isize, color: Color) {
+        if y < 0 || y as usize >= self.height {
+            return;
+        }
+        let x0 = x0.max(0);
+        let x1 = x1.min(self.width as isize - 1);
+        for x in x0..=x1 {
+            self.set_pixel(x, y, color);
+        }
+    }
+
+    /// part of the segment inside the frame grown by `margin` pixels,
+    /// liang-barsky; `None` when it misses the frame or is not finite
+    fn clip(
+        &self,
+        from: Vector2D<f32>,
+        to: Vector2D<f32>,
+        margin: f32,
+    ) -> Option<(Vector2D<f32>, Vector2D<f32>)> {
+        if ![from.x, from.y, to.x, to.y].iter().all(|v| v.is_finite()) {
+            return None;
+        }
+        let (min, max) = (
+            -margin,
+            Vector2D::new(self.width as f32, self.height as f32).vadd(margin),
+        );
+        let d = from.delta(to);
+        let (mut enter, mut exit) = (0.0f32, 1.0f32);
+        for (p, q) in [
+            (-d.x, from.x - min),
+            (d.x, max.x - from.x),
+            (-d.y, from.y - min),
+            (d.y, max.y - from.y),
+        ] {
+            if p == 0.0 {
+                // parallel to this edge, all in or all out
+                if q < 0.0 {
+                    return None;
+                }
+            } else if p < 0.0 {
+                enter = enter.max(q / p);
+            } else {
+                exit = exit.min(q / p);
+            }
+        }
+        if enter > exit {
+            return None;
+        }
+        Some((from.add(d.vmul(enter)), from.add(d.vmul(exit))))
+    }
+
+    /// bresenham line
+    pub fn line(&mut self, from: Vector2D<f32>, to: Vector2D<f32>, color: impl Into<Color>) {
+        let color = color.into();
+        let Some((from, to)) = self.clip(from, to, 1.0) else {
+            return;
+        };
+        let (mut x0, mut y0) = (from.x.floor() as isize, from.y.floor() as isize);
+        let (x1, y1) = (to.x.floor() as isize, to.y.floor() as isize);
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let sx = if x0 < x1 { 1 } else { -1 };
+        let sy = if y0 < y1 { 1 } else { -1 };
+        let mut err = dx + dy;
+
+        loop {
+            self.set_pixel(x0, y0, color);
+            if x0 == x1 && y0 == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x0 += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y0 += sy;
+            }
+        }
+    }
+
+    /// xiaolin wu antialiased line
+    pub fn line_aa(&mut self, from: Vector2D<f32>, to: Vector2D<f32>, color: impl Into<Color>) {
+        let color = color.into();
+        let Some((from, to)) = self.clip(from, to, 2.0) else {
+            return;
+        };
+        let steep = (to.y - from.y).abs() > (to.x - from.x).abs();
+        let (mut a, mut b) = (from, to);
+        if steep {
+            a = Vector2D { x: a.y, y: a.x };
+            b = Vector2D { x: b.y, y: b.x };
+        }
+        if a.x > b.x {
+            std::mem::swap(&mut a, &mut b);
+        }
+
+        let dx = b.x - a.x;
+        let gradient = if dx == 0.0 { 1.0 } else { (b.y - a.y) / dx };
+        let plot = |frame: &mut Frame, x: isize, y: isize, coverage: f32| {
+            if steep {
+                frame.cover_pixel(y, x, color, coverage);
+            } else {
+                frame.cover_pixel(x, y, color, coverage);
+            }
+        };
+
+        let start = a.x.round();
+        let end = b.x.round();
+        let mut y = a.y + gradient * (start - a.x);
+        for x in start as isize..=end as isize {
+            let fract = y - y.floor();
+            plot(self, x, y.floor() as isize, 1.0 - fract);
+            plot(self, x, y.floor() as isize + 1, fract);
+            y += gradient;
+        }
+    }
+
+    pub fn circle(&mut self, center: Vector2D<f32>, radius: f32, color: impl Into<Color>) {
+        let color = color.into();
+        let (cx, cy) = (center.x.floor() as isize, center.y.floor() as isize);
+        let mut x = radius.round() as isize;
+        let mut y = 0;
+        let mut err = 1 - x;
+
+        while x >= y {
+            for (px, py) in [
+                (x, y),
+                (y, x),
+                (-y, x),
+                (-x, y),
+                (-x, -y),
+                (-y, -x),
+                (y, -x),
+                (x, -y),
+            ] {
+                self.set_pixel(cx + px, cy + py, color);
+            }
+            y += 1;
+            if err < 0 {
+                err += 2 * y + 1;
+            } else {
+                x -= 1;
+                err += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    /// every pixel whose center is inside the circle, at least one pixel
+    pub fn fill_circle(&mut self, center: Vector2D<f32>, radius: f32, color: impl Into<Color>) {
+        let color = color.into();
+        if ![center.x, center.y, radius].iter().all(|v| v.is_finite()) {
+            return;
+        }
+        if radius < 1.0 {
+            self.set_pixel(center.x.floor() as isize, center.y.floor() as isize, color);
+            return;
+        }
+
+        let top = ((center.y - radius - 0.5).ceil() as isize).max(0);
+        let bottom = ((center.y + radius - 0.5).floor() as isize).min(self.height as isize - 1);
+        for y in top..=bottom {
+            let dy = y as f32 + 0.5 - center.y;
+            let half = (radius * radius - dy * dy).max(0.0).sqrt();
+            let x0 = (center.x - half - 0.5).ceil() as isize;
+            let x1 = (center.x + half - 0.5).floor() as isize;
+            self.span(y, x0, x1, color);
+        }
+    }
+
+    pub fn rect(&mut self, pos: Vector2D<f32>, size: Vector2D<f32>, color: impl Into<Color>) {
+        let color = color.into();
+        let corners = [
+            pos,
+            Vector2D::new(pos.x + size.x - 1.0, pos.y),
+            Vector2D::new(pos.x + size.x - 1.0, pos.y + size.y - 1.0),
+            Vector2D::new(pos.x, pos.y + size.y - 1.0),
+        ];
+        self.polygon(&corners, color);
+    }
+
+    pub fn fill_rect(&mut self, pos: Vector2D<f32>, size: Vector2D<f32>, color: impl Into<Color>) {
+        let color = color.into();
+        if ![pos.x, pos.y, size.x, size.y].iter().all(|v| v.is_finite()) {
+            return;
+        }
+        let x0 = pos.x.floor() as isize;
+        let x1 = ((pos.x + size.x).floor() as isize).saturating_sub(1);
+        let top = (pos.y.floor() as isize).max(0);
+        let bottom = ((pos.y + size.y).floor() as isize).min(self.height as isize);
+        for y in top..bottom {
+            self.span(y, x0, x1, color);
+        }
+    }
+
+    /// closed outline through `points`
+    pub fn polygon(&mut self, points: &[Vector2D<f32>], color: impl Into<Color>) {
+        let color = color.into();
+        for (i, &point) in points.iter().enumerate() {
+            self.line(point, points[(i + 1) % points.len()], color);
+        }
+    }
+
+    /// scanline fill with the even-odd rule, sampled at pixel centers
+    pub fn fill_polygon(&mut self, points: &[Vector2D<f32>], color: impl Into<Color>) {
+        let color = color.into();
+        if points.len() < 3 {
+            return;
+        }
+        let top = points.iter().map(|p| p.y).fold(f32::INFINITY, f32::min);
+        let bottom = points.iter().map(|p| p.y).fold(f32::NEG_INFINITY, f32::max);
+        let top = ((top - 0.5).ceil() as isize).max(0);
+        let bottom = ((bottom - 0.5).floor() as isize).min(self.height as isize - 1);
+
+        let mut crossings: Vec<f32> = Vec::new();
+        for y in top..=bottom {
+            let sample = y as f32 + 0.5;
+            crossings.clear();
+            for (i, &a) in points.iter().enumerate() {
+                let b = points[(i + 1) % points.len()];
+                if (a.y <= sample) != (b.y <= sample) {
+                    crossings.push(a.x + (sample - a.y) / (b.y - a.y) * (b.x - a.x));
+                }
+            }
+            crossings.sort_by(f32::total_cmp);
+            for pair in crossings.chunks_exact(2) {
+                let x0 = (pair[0] - 0.5).ceil() as isize;
+                let x1 = (pair[1] - 0.5).floor() as isize;
+                self.span(y, x0, x1, color);
+            }
+        }
+    }
+
+    /// `pos` is the top left corner of the first character, `\n` starts a
+    /// new line
+    pub fn draw_text(&mut self, pos: Vector2D<f32>, text: &str, color: impl Into<Color>) {
+        let color = color.into();
+        let (left, mut top) = (pos.x.floor() as isize, pos.y.floor() as isize);
+        for line in text.lines() {
+            let mut x = left;
+            for ch in line.chars() {
+                for (dy, row) in font::glyph(ch).iter().enumerate() {
+                    for dx in 0..font::GLYPH_WIDTH {
+                        if row & (1 << (font::GLYPH_WIDTH - 1 - dx)) != 0 {
+                            self.set_pixel(x + dx as isize, top + dy as isize, color);
+                        }
+                    }
+                }
+                x += font::ADVANCE as isize;
+            }
+            top += font::LINE_HEIGHT as isize;
+        }
+    }
 }
@@ -0,0 +1,111 @@
+use crate::camera::View;
+use crate::color::Color;
+use crate::entities::Commands;
+use crate::entity::{
+    Drawable, Entity, FrameInfo, Inputable, Layer, Restore, Snapshotable, Updatable, restore_with,
+};
+use crate::font;
+use crate::frame::Frame;
+use crate::input::Input;
+use crate::snapshot::{Record, SnapshotError};
+use crate::vector::Vector2D;
+
+const MARGIN: f32 = 8.0;
+const PADDING: f32 = 4.0;
+const BACKGROUND: Color = Color::rgba(0, 0, 0, 160);
+const TEXT_COLOR: Color = Color::rgb(220, 220, 220);
+
+/// text panel in the top left corner with the core and entity stats
+pub struct Hud {
+    text: String,
+    visible: bool,
+}
+
+impl Default for Hud {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hud {
+    pub fn new() -> Self {
+        Self {
+            text: String::new(),
+            visible: true,
+        }
+    }
+}
+
+impl Inputable for Hud {
+    fn handle_input(&mut self, input: &Input) {
+        if input.action("hud").pressed {
+            self.visible = !self.visible;
+        }
+    }
+}
+
+impl Updatable for Hud {
+    fn update(&mut self, _dt: f32, _commands: &mut Commands) {}
+}
+
+impl Drawable for Hud {
+    fn draw(&self, frame: &mut Frame, _view: &View) {
+        if !self.visible {
+            return;
+        }
+        let (width, height) = font::text_size(&self.text);
+        frame.fill_rect(
+            Vector2D::new(MARGIN, MARGIN),
+            Vector2D::new(width as f32 + PADDING * 2.0, height as f32 + PADDING * 2.0),
+            BACKGROUND,
+        );
+        frame.draw_text(
+            Vector2D::new(MARGIN + PADDING, MARGIN + PADDING),
+            &self.text,
+            TEXT_COLOR,
+        );
+    }
+}
+
+impl Snapshotable for Hud {
+    fn save(&self) -> Record {
+        Record::new("hud")
+    }
+
+    fn restore(&self, record: &mut Record) -> Result<Restore, SnapshotError> {
+        record.expect_kind("hud")?;
+        Ok(restore_with(|_: &mut Self| {}))
+    }
+}
+
+impl Entity for Hud {
+    fn name(&self) -> &'static str {
+        "hud"
+    }
+
+    fn layer(&self) -> Layer {
+        Layer::Ui
+    }
+
+    fn observe(&mut self, info: &FrameInfo) {
+        let mut text = format!(
+            "fps {:.0}\ndt {:.2} ms\ntool {}",
+            info.fps,
+            info.dt * 1000.0,
+            info.tool
+        );
+        if info.paused {
+            text += "\npaused";
+        }
+        for (name, values) in &info.entities {
+            if values.is_empty() {
+                continue;
+            }
+            text += &format!("\n\n{name}");
+            for (label, value) in values {
+                text += &format!("\n  {label} {value}");
+            }
+        }
+        self.text = text;
+    }
+}
@@ -1,38 +1,216 @@
+use crate::action::{ActionMap, ActionState, Actions};
+use crate::camera::Camera;
+use crate::key::{Key, KeySet};
 use crate::vector::Vector2D;
-use minifb::{MouseButton, MouseMode, Window};
+
+/// raw device state as polled from a backend
+#[derive(Default, Copy, Clone, Debug)]
+pub struct InputState {
+    pub mouse_pos: Option<Vector2D<f32>>,
+    pub mouse_abs_pos: Option<Vector2D<f32>>,
+    pub mouse_left: bool,
+    pub mouse_middle: bool,
+    pub mouse_right: bool,
+    pub mouse_wheel: Vector2D<f32>,
+    pub keys: KeySet,
+}
+
+impl InputState {
+    /// same state with the buttons up and the wheel still
+    pub fn without_mouse_buttons(&self) -> Self {
+        Self {
+            mouse_left: false,
+            mouse_middle: false,
+            mouse_right: false,
+            mouse_wheel: Vector2D { x: 0.0, y: 0.0 },
+            ..*self
+        }
+    }
+}
+
+const DOUBLE_CLICK_TIME: f32 = 0.3;
+const DOUBLE_CLICK_DIST: f32 = 4.0;
+const VELOCITY_SMOOTHING: f32 = 0.5;
+
+#[derive(Default, Copy, Clone, Debug)]
+pub struct Button {
+    pub held: bool,
+    pub pressed: bool,
+    pub released: bool,
+    /// consecutive clicks, 2 on a double click
+    pub clicks: usize,
+    /// where the button went down, kept until the frame it is released
+    pub drag_start: Option<Vector2D<f32>>,
+    last_press: Option<(f32, Vector2D<f32>)>,
+}
+
+impl Button {
+    fn refresh(&mut self, down: bool, pos: Vector2D<f32>, time: f32) {
+        if self.released {
+            self.drag_start = None;
+        }
+        self.pressed = down && !self.held;
+        self.released = !down && self.held;
+        self.held = down;
+
+        if self.pressed {
+            self.clicks = match self.last_press {
+                Some((last, last_pos))
+                    if time - last <= DOUBLE_CLICK_TIME
+                        && last_pos.delta(pos).length() <= DOUBLE_CLICK_DIST =>
+                {
+                    self.clicks + 1
+                }
+                _ => 1,
+            };
+            self.last_press = Some((time, pos));
+            self.drag_start = Some(pos);
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+}
+
+impl MouseButton {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "left" => Some(MouseButton::Left),
+            "middle" => Some(MouseButton::Middle),
+            "right" => Some(MouseButton::Right),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Default, Copy, Clone, Debug)]
 pub struct Mouse {
     pub pos: Vector2D<f32>,
     pub abs_pos: Vector2D<f32>,
-    pub left: bool,
-    pub middle: bool,
-    pub right: bool,
+    pub left: Button,
+    pub middle: Button,
+    pub right: Button,
+    /// movement since the last frame
+    pub delta: Vector2D<f32>,
+    /// smoothed movement in pixels per second
+    pub velocity: Vector2D<f32>,
+    /// scroll since the last frame, positive y scrolls up
+    pub wheel: Vector2D<f32>,
+    /// `pos` seen through the camera
+    pub world_pos: Vector2D<f32>,
+    /// `velocity` seen through the camera
+    pub world_velocity: Vector2D<f32>,
+    time: f32,
 }
 
 impl Mouse {
-    fn refresh(&mut self, window: &Window) {
-        if let Some(pos) = window.get_mouse_pos(MouseMode::Clamp) {
-            self.pos.x = pos.0;
-            self.pos.y = pos.1;
+    pub fn button(&self, button: MouseButton) -> &Button {
+        match button {
+            MouseButton::Left => &self.left,
+            MouseButton::Middle => &self.middle,
+            MouseButton::Right => &self.right,
         }
-        if let Some(pos) = window.get_mouse_pos(MouseMode::Pass) {
-            self.abs_pos.x = pos.0;
-            self.abs_pos.y = pos.1;
+    }
+
+    fn refresh(&mut self, state: &InputState, dt: f32) {
+        let prev = self.pos;
+        if let Some(pos) = state.mouse_pos {
+            self.pos = pos;
         }
-        self.left = window.get_mouse_down(MouseButton::Left);
-        self.middle = window.get_mouse_down(MouseButton::Middle);
-        self.right = window.get_mouse_down(MouseButton::Right);
+        if let Some(pos) = state.mouse_abs_pos {
+            self.abs_pos = pos;
+        }
+        self.delta = prev.delta(self.pos);
+        self.wheel = state.mouse_wheel;
+        if dt > 0.0 {
+            self.velocity = self.velocity.lerp(self.delta.vdiv(dt), VELOCITY_SMOOTHING);
+        }
+
+        self.time += dt;
+        self.left.refresh(state.mouse_left, self.pos, self.time);
+        self.middle.refresh(state.mouse_middle, self.pos, self.time);
+        self.right.refresh(state.mouse_right, self.pos, self.time);
     }
 }
 
 #[derive(Default, Copy, Clone, Debug)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub logo: bool,
+}
+
+#[derive(Default, Copy, Clone, Debug)]
+pub struct Keyboard {
+    held: KeySet,
+    pressed: KeySet,
+    released: KeySet,
+    pub modifiers: Modifiers,
+}
+
+impl Keyboard {
+    fn refresh(&mut self, state: &InputState) {
+        self.pressed = state.keys.difference(self.held);
+        self.released = self.held.difference(state.keys);
+        self.held = state.keys;
+
+        let held = |left, right| self.held.contains(left) || self.held.contains(right);
+        self.modifiers = Modifiers {
+            shift: held(Key::LeftShift, Key::RightShift),
+            ctrl: held(Key::LeftCtrl, Key::RightCtrl),
+            alt: held(Key::LeftAlt, Key::RightAlt),
+            logo: held(Key::LeftSuper, Key::RightSuper),
+        };
+    }
+
+    /// key is down this frame
+    pub fn held(&self, key: Key) -> bool {
+        self.held.contains(key)
+    }
+
+    /// key went down this frame
+    pub fn pressed(&self, key: Key) -> bool {
+        self.pressed.contains(key)
+    }
+
+    /// key went up this frame
+    pub fn released(&self, key: Key) -> bool {
+        self.released.contains(key)
+    }
+}
+
+#[derive(Default, Clone, Debug)]
 pub struct Input {
     pub mouse: Mouse,
+    pub keyboard: Keyboard,
+    map: ActionMap,
+    actions: Actions,
 }
 
 impl Input {
-    pub fn refresh(&mut self, window: &Window) {
-        self.mouse.refresh(window);
+    pub fn set_action_map(&mut self, map: ActionMap) {
+        self.map = map;
+        self.actions = Actions::default();
+    }
+
+    pub fn refresh(&mut self, state: &InputState, dt: f32) {
+        self.mouse.refresh(state, dt);
+        self.keyboard.refresh(state);
+        self.actions.refresh(&self.map, &self.mouse, &self.keyboard);
+    }
+
+    /// convert the mouse position to world space
+    pub fn project(&mut self, camera: &Camera) {
+        self.mouse.world_pos = camera.screen_to_world(self.mouse.pos);
+        self.mouse.world_velocity = self.mouse.velocity.vdiv(camera.zoom);
+    }
+
+    pub fn action(&self, name: &str) -> ActionState {
+        self.actions.get(name)
     }
 }
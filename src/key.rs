@@ -0,0 +1,67 @@
+macro_rules! keys {
+    ($($name:ident),* $(,)?) => {
+        /// every key a backend can report
+        #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+        pub enum Key {
+            $($name),*
+        }
+
+        impl Key {
+            pub const ALL: &[Key] = &[$(Key::$name),*];
+
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(Key::$name => stringify!($name)),*
+                }
+            }
+        }
+    };
+}
+
+keys! {
+    Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9, A, B, C, D, E, F, G, H, I, J, K,
+    L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11,
+    F12, F13, F14, F15, Down, Left, Right, Up, Apostrophe, Backquote, Backslash, Comma, Equal,
+    LeftBracket, Minus, Period, RightBracket, Semicolon, Slash, Backspace, Delete, End, Enter,
+    Escape, Home, Insert, Menu, PageDown, PageUp, Pause, Space, Tab, NumLock, CapsLock,
+    ScrollLock, LeftShift, RightShift, LeftCtrl, RightCtrl, LeftAlt, RightAlt, LeftSuper,
+    RightSuper, NumPad0, NumPad1, NumPad2, NumPad3, NumPad4, NumPad5, NumPad6, NumPad7, NumPad8,
+    NumPad9, NumPadDot, NumPadSlash, NumPadAsterisk, NumPadMinus, NumPadPlus, NumPadEnter,
+}
+
+impl Key {
+    /// case insensitive lookup of `Key::name`
+    pub fn from_name(name: &str) -> Option<Key> {
+        Key::ALL
+            .iter()
+            .copied()
+            .find(|key| key.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// set of keys packed in a bit field
+#[derive(Default, Copy, Clone, Debug, PartialEq)]
+pub struct KeySet(u128);
+
+impl KeySet {
+    pub fn from_bits(bits: u128) -> Self {
+        KeySet(bits)
+    }
+
+    pub fn bits(&self) -> u128 {
+        self.0
+    }
+
+    pub fn insert(&mut self, key: Key) {
+        self.0 |= 1 << key as u32;
+    }
+
+    pub fn contains(&self, key: Key) -> bool {
+        self.0 & (1 << key as u32) != 0
+    }
+
+    /// keys in `self` but not in `other`
+    pub fn difference(&self, other: KeySet) -> KeySet {
+        KeySet(self.0 & !other.0)
+    }
+}
@@ -0,0 +1,30 @@
+//! soft body playground: a fixed step `Core` running boxed entities, a
+//! software `Frame` to draw them and the slime and tortilla simulations
+
+pub mod action;
+pub mod backend;
+pub mod camera;
+pub mod capture;
+pub mod color;
+pub mod core;
+mod debug;
+pub mod delaunay;
+pub mod entities;
+pub mod entity;
+mod font;
+pub mod frame;
+pub mod hud;
+pub mod input;
+pub mod key;
+mod macros;
+pub mod metaball;
+pub mod param;
+pub mod particle;
+pub mod replay;
+pub mod rng;
+pub mod scene;
+pub mod snapshot;
+pub mod spatial_grid;
+pub mod tortilla;
+pub mod ui;
+pub mod vector;
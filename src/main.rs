@@ -1,59 +1,127 @@
-mod core;
-mod entity;
-mod frame;
-mod input;
-mod macros;
-mod particle;
-mod spatial_grid;
-mod tortilla;
-mod vector;
-
-use core::Core;
-use particle::ParticleSystem;
-use std::time::Instant;
-use tortilla::Tortilla;
-use vector::Vector2D;
-
-const TITLE: &str = "slime";
-const WIDTH: usize = 800;
-const HEIGHT: usize = 600;
-const REFRESH: usize = 60;
+mod cli;
+
+use cli::{Options, USAGE};
+use slime::action::ActionMap;
+use slime::backend::{NullBackend, WindowBackend};
+use slime::capture::{Capture, GifRecorder};
+use slime::core::Core;
+use slime::hud::Hud;
+use slime::replay::{InputRecorder, InputReplay};
+use slime::scene::{Scene, SceneWatcher};
+use std::fmt::Display;
+use std::path::Path;
 
 fn main() {
-    let tortilla = Tortilla::new(
-        Vector2D {
-            x: WIDTH,
-            y: HEIGHT,
-        },
-        Vector2D::new(200.0, 300.0),
-        0.5,
-        3.0,
-        10,
-        20.0,
-    );
-    let particle_system = ParticleSystem::new(
-        Vector2D {
-            x: WIDTH,
-            y: HEIGHT,
-        },
-        Vector2D::new(600.0, 300.0),
-        6400,
-        0.5,
-    );
-
-    let mut core = Core::new(TITLE, WIDTH, HEIGHT, REFRESH);
-    core.add_entity(tortilla);
-    core.add_entity(particle_system);
-
-    let mut last = Instant::now();
-    while core.is_open() {
-        let now = Instant::now();
-        let dt = now.duration_since(last).as_secs_f32();
-        last = now;
-
-        core.analyze_event();
-        core.update(dt);
-        core.draw();
-        core.next_frame();
+    let options = Options::parse(std::env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("slime: {err}\nrun `slime --help` for the list of options");
+        std::process::exit(2);
+    });
+    if options.help {
+        print!("{USAGE}");
+        return;
+    }
+
+    // the scene lists the window and entities, see scenes/default.scene,
+    // a file given with `--scene` is watched and edits apply while running
+    let overrides = options.overrides();
+    let scene_name = options.scene.as_deref().unwrap_or("default scene");
+    let scene = match &options.scene {
+        Some(path) => Scene::load(Path::new(path), &overrides),
+        None => Scene::parse(Scene::DEFAULT, &overrides),
+    };
+    let scene = scene.unwrap_or_else(|err| exit_with(scene_name, err));
+    let window = &scene.window;
+
+    let mut core = Core::new(window.width, window.height, window.refresh)
+        .with_physics(window.physics)
+        .with_max_steps(window.max_steps);
+    if options.layer_frames {
+        core = core.with_offscreen_layers();
+    }
+    if let Some(capture) = capture(&options) {
+        core = core.with_capture(capture);
+    }
+    if let Some(gif) = gif_recorder(&options) {
+        core = core.with_gif_recorder(gif);
+    }
+    if let Some(path) = &options.actions {
+        let map = ActionMap::load(Path::new(path)).unwrap_or_else(|err| exit_with(path, err));
+        core = core.with_action_map(map);
     }
+    if let Some(path) = &options.replay {
+        let replay = InputReplay::load(Path::new(path)).unwrap_or_else(|err| exit_with(path, err));
+        core = core.with_replay(replay);
+    }
+    if let Some(path) = &options.record {
+        let recorder = InputRecorder::create(Path::new(path), core.step())
+            .unwrap_or_else(|err| exit_with(path, err));
+        core = core.with_input_recorder(recorder);
+    }
+    let ids = scene
+        .spawn(core.entities_mut())
+        .unwrap_or_else(|err| exit_with(scene_name, err));
+    if let Some(path) = &options.scene {
+        let watcher = SceneWatcher::new(path, scene.clone(), ids).with_overrides(overrides);
+        core = core.with_scene_watcher(watcher);
+    }
+    core.add_entity(Hud::new());
+
+    if let Some(path) = &options.restore {
+        core = core.with_snapshot_path(path);
+        if let Err(err) = core.load_snapshot(Path::new(path)) {
+            exit_with(path, err);
+        }
+    }
+
+    match options.headless {
+        Some(frames) => {
+            // one physics step per frame keeps headless runs reproducible
+            let mut backend = NullBackend::new(window.width, window.height)
+                .with_frames(frames)
+                .with_frame_time(core.step());
+            core.run(&mut backend);
+            println!("{:08x}", checksum(&backend.frame().buffer));
+        }
+        None => {
+            let mut backend =
+                WindowBackend::new(&window.title, window.width, window.height, window.refresh);
+            core.run(&mut backend);
+        }
+    }
+}
+
+/// report an error about the file `name` and stop
+fn exit_with(name: &str, err: impl Display) -> ! {
+    eprintln!("{name}: {err}");
+    std::process::exit(1);
+}
+
+fn capture(options: &Options) -> Option<Capture> {
+    let mut capture = Capture::new(options.capture.as_ref()?).with_enabled();
+    if let Some(every) = options.capture_every {
+        capture = capture.with_every(every);
+    }
+    if let Some(formats) = &options.capture_formats {
+        capture = capture.with_formats(formats.clone());
+    }
+    Some(capture)
+}
+
+/// FNV-1a hash of the last frame, to compare headless runs
+fn checksum(buffer: &[u32]) -> u32 {
+    let mut hash: u32 = 0x811c9dc5;
+    for pixel in buffer {
+        for byte in pixel.to_le_bytes() {
+            hash ^= byte as u32;
+            hash = hash.wrapping_mul(0x01000193);
+        }
+    }
+    hash
+}
+
+/// gifs go to the capture directory, `capture` when none is given
+fn gif_recorder(options: &Options) -> Option<GifRecorder> {
+    let frames = options.gif_frames?;
+    let dir = options.capture.as_deref().unwrap_or("capture");
+    Some(GifRecorder::new(dir, frames).with_every(2).with_scale(2))
 }
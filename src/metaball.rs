@@ -0,0 +1,166 @@
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::frame::Frame;
+use crate::spatial_grid::SpatialGrid;
+use crate::vector::Vector2D;
+
+const X_HASH: usize = 6287364878;
+const Y_HASH: usize = 2731859790;
+const GRID_TILES: usize = 4096;
+/// screen pixels between two samples of the field
+const LATTICE_STEP: usize = 4;
+/// field value of the blob surface, a lone ball reaches it at 0.54 radius
+const THRESHOLD: f32 = 0.5;
+const EDGE_COLOR: Color = Color::rgb(40, 140, 40);
+const CORE_COLOR: Color = Color::rgb(200, 255, 120);
+const OUTLINE_COLOR: Color = Color::rgb(230, 255, 200);
+
+/// implicit surface around a set of points, each point adding a smooth
+/// bump of `radius` to a scalar field drawn where it crosses `THRESHOLD`
+pub struct Metaballs {
+    grid: SpatialGrid,
+    radius: f32,
+}
+
+impl Metaballs {
+    pub fn new(radius: f32) -> Self {
+        Self {
+            grid: SpatialGrid::new(
+                GRID_TILES,
+                radius,
+                Vector2D {
+                    x: X_HASH,
+                    y: Y_HASH,
+                },
+            ),
+            radius,
+        }
+    }
+
+    /// index the points, ids are their position in `points`
+    pub fn update(&mut self, points: &[Vector2D<f32>]) {
+        self.grid.clear();
+        for (id, &point) in points.iter().enumerate() {
+            // the grid skips the last tile of the range, twice the radius
+            // still covers every tile within one radius of the point
+            self.grid.push(id, point, self.radius * 2.0);
+        }
+    }
+
+    /// `points` may have moved a little since `update`, the lookups are
+    /// loose enough for that
+    fn field(&self, points: &[Vector2D<f32>], pos: Vector2D<f32>) -> f32 {
+        let radius_sq = self.radius * self.radius;
+        let mut sum = 0.0;
+        for &id in self.grid.get_at(pos) {
+            let Some(point) = points.get(id) else {
+                continue;
+            };
+            let d = point.delta(pos);
+            let dist_sq = d.x * d.x + d.y * d.y;
+            if dist_sq < radius_sq {
+                let falloff = 1.0 - dist_sq / radius_sq;
+                sum += falloff * falloff;
+            }
+        }
+        sum
+    }
+
+    pub fn draw(&self, frame: &mut Frame, camera: &Camera, points: &[Vector2D<f32>]) {
+        let cols = frame.width / LATTICE_STEP + 2;
+        let rows = frame.height / LATTICE_STEP + 2;
+        let node = |x: usize, y: usize| {
+            Vector2D::new((x * LATTICE_STEP) as f32, (y * LATTICE_STEP) as f32)
+        };
+
+        let mut lattice = vec![0.0; cols * rows];
+        let mut max: f32 = THRESHOLD;
+        for y in 0..rows {
+            for x in 0..cols {
+                let value = self.field(points, camera.screen_to_world(node(x, y)));
+                lattice[y * cols + x] = value;
+                max = max.max(value);
+            }
+        }
+
+        for y in 0..rows - 1 {
+            for x in 0..cols - 1 {
+                let corners = [
+                    lattice[y * cols + x],
+                    lattice[y * cols + x + 1],
+                    lattice[(y + 1) * cols + x + 1],
+                    lattice[(y + 1) * cols + x],
+                ];
+                if corners.iter().all(|&value| value < THRESHOLD) {
+                    continue;
+                }
+                self.fill_cell(frame, node(x, y), corners, max);
+                outline_cell(frame, node(x, y), corners);
+            }
+        }
+    }
+
+    /// per pixel bilinear field, brighter towards the densest point
+    fn fill_cell(&self, frame: &mut Frame, origin: Vector2D<f32>, corners: [f32; 4], max: f32) {
+        let [top_left, top_right, bottom_right, bottom_left] = corners;
+        let step = LATTICE_STEP as f32;
+        for py in 0..LATTICE_STEP {
+            let v = py as f32 / step;
+            let left = top_left + (bottom_left - top_left) * v;
+            let right = top_right + (bottom_right - top_right) * v;
+            for px in 0..LATTICE_STEP {
+                let value = left + (right - left) * (px as f32 / step);
+                if value < THRESHOLD {
+                    continue;
+                }
+                let depth = ((value - THRESHOLD) / (max - THRESHOLD)).sqrt();
+                frame.set_pixel(
+                    origin.x as isize + px as isize,
+                    origin.y as isize + py as isize,
+                    EDGE_COLOR.lerp(CORE_COLOR, depth),
+                );
+            }
+        }
+    }
+}
+
+/// marching squares segments of the surface inside one lattice cell
+fn outline_cell(frame: &mut Frame, origin: Vector2D<f32>, corners: [f32; 4]) {
+    let step = LATTICE_STEP as f32;
+    let pos = [
+        origin,
+        Vector2D::new(origin.x + step, origin.y),
+        Vector2D::new(origin.x + step, origin.y + step),
+        Vector2D::new(origin.x, origin.y + step),
+    ];
+    // point where the edge from corner `a` to corner `b` crosses the surface
+    let edge = |a: usize, b: usize| {
+        let t = (THRESHOLD - corners[a]) / (corners[b] - corners[a]);
+        pos[a].lerp(pos[b], t)
+    };
+    let (top, right, bottom, left) = (edge(0, 1), edge(1, 2), edge(3, 2), edge(0, 3));
+
+    let case = corners
+        .iter()
+        .enumerate()
+        .filter(|(_, value)| **value >= THRESHOLD)
+        .fold(0, |case, (i, _)| case | (1 << i));
+    let center = corners.iter().sum::<f32>() / 4.0 >= THRESHOLD;
+    let segments: &[(Vector2D<f32>, Vector2D<f32>)] = match case {
+        0 | 15 => &[],
+        1 | 14 => &[(left, top)],
+        2 | 13 => &[(top, right)],
+        3 | 12 => &[(left, right)],
+        4 | 11 => &[(right, bottom)],
+        6 | 9 => &[(top, bottom)],
+        7 | 8 => &[(left, bottom)],
+        // saddles, the center sample decides which corners connect
+        5 if center => &[(left, bottom), (top, right)],
+        5 => &[(left, top), (right, bottom)],
+        10 if center => &[(left, top), (right, bottom)],
+        _ => &[(left, bottom), (top, right)],
+    };
+    for &(from, to) in segments {
+        frame.line_aa(from, to, OUTLINE_COLOR);
+    }
+}
@@ -0,0 +1,84 @@
+/// borrowed handle on one tunable value of an entity
+pub enum ParamValue<'a> {
+    Float {
+        value: &'a mut f32,
+        min: f32,
+        max: f32,
+    },
+    Int {
+        value: &'a mut usize,
+        min: usize,
+        max: usize,
+    },
+    Bool(&'a mut bool),
+}
+
+pub struct Param<'a> {
+    pub name: &'static str,
+    pub value: ParamValue<'a>,
+}
+
+impl<'a> Param<'a> {
+    pub fn float(name: &'static str, value: &'a mut f32, min: f32, max: f32) -> Self {
+        Self {
+            name,
+            value: ParamValue::Float { value, min, max },
+        }
+    }
+
+    pub fn int(name: &'static str, value: &'a mut usize, min: usize, max: usize) -> Self {
+        Self {
+            name,
+            value: ParamValue::Int { value, min, max },
+        }
+    }
+
+    pub fn toggle(name: &'static str, value: &'a mut bool) -> Self {
+        Self {
+            name,
+            value: ParamValue::Bool(value),
+        }
+    }
+
+    /// position of the value in its range, from 0.0 to 1.0 even when the
+    /// value was set outside of it
+    pub fn ratio(&self) -> f32 {
+        let ratio = match &self.value {
+            ParamValue::Float { value, min, max } => (**value - min) / (max - min),
+            ParamValue::Int { value, min, max } => {
+                value.saturating_sub(*min) as f32 / max.saturating_sub(*min).max(1) as f32
+            }
+            ParamValue::Bool(value) => **value as u8 as f32,
+        };
+        ratio.clamp(0.0, 1.0)
+    }
+
+    pub fn in_range(&self) -> bool {
+        match &self.value {
+            ParamValue::Float { value, min, max } => (*min..=*max).contains(*value),
+            ParamValue::Int { value, min, max } => (*min..=*max).contains(*value),
+            ParamValue::Bool(_) => true,
+        }
+    }
+
+    /// set the value from a position in its range, clamped
+    pub fn set_ratio(&mut self, ratio: f32) {
+        let ratio = ratio.clamp(0.0, 1.0);
+        match &mut self.value {
+            ParamValue::Float { value, min, max } => **value = *min + (*max - *min) * ratio,
+            ParamValue::Int { value, min, max } => {
+                **value = *min + ((*max - *min) as f32 * ratio).round() as usize
+            }
+            ParamValue::Bool(value) => **value = ratio >= 0.5,
+        }
+    }
+
+    pub fn display(&self) -> String {
+        match &self.value {
+            ParamValue::Float { value, .. } if value.abs() >= 100.0 => format!("{value:.0}"),
+            ParamValue::Float { value, .. } => format!("{value:.3}"),
+            ParamValue::Int { value, .. } => value.to_string(),
+            ParamValue::Bool(value) => String::from(if **value { "on" } else { "off" }),
+        }
+    }
+}
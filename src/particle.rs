@@ -1,16 +1,64 @@
-use crate::entity::{Drawable, Entity, Inputable, Updatable};
+use crate::camera::View;
+use crate::color::{BlendMode, Color};
+use crate::debug;
+use crate::entities::Commands;
+use crate::entity::{Drawable, Entity, Inputable, Restore, Snapshotable, Updatable, restore_with};
 use crate::frame::Frame;
 use crate::input::Input;
+use crate::metaball::Metaballs;
+use crate::param::Param;
+use crate::rng::Rng;
+use crate::snapshot::{Record, SnapshotError};
 use crate::spatial_grid::SpatialGrid;
 use crate::vector::Vector2D;
 use crate::{dot, rgb};
 
 const X_HASH: usize = 6287364878;
 const Y_HASH: usize = 2731859790;
+const ANCHOR_NUDGE: f32 = 2.0;
+const DEFAULT_PULL: f32 = 1000.0;
+const MIN_PULL: f32 = 50.0;
+const MAX_PULL: f32 = 20000.0;
+const PULL_STEP: f32 = 1.1;
+/// reach of one particle in the metaball field, in world units
+const METABALL_RADIUS: f32 = 3.0;
+/// max offset of a seeded particle from its spawn point, in world units,
+/// below half the lattice spacing so particles never swap places
+const JITTER: f32 = 0.4;
+/// seconds of travel drawn by the debug velocity vectors
+const VELOCITY_SCALE: f32 = 0.05;
+
+/// tunable physics of a `ParticleSystem`
+#[derive(Copy, Clone, Debug)]
+pub struct ParticleParams {
+    /// acceleration towards the anchor
+    pub pull: f32,
+    /// speed kept after each step
+    pub damping: f32,
+    /// bounciness of particle on particle collisions
+    pub restitution: f32,
+}
+
+impl Default for ParticleParams {
+    fn default() -> Self {
+        Self {
+            pull: DEFAULT_PULL,
+            damping: 0.95,
+            restitution: 0.5,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum RenderMode {
+    Points,
+    Metaballs,
+}
 
 #[derive(Default, Copy, Clone, Debug)]
 struct Particle {
     pos: Vector2D<f32>,
+    prev: Vector2D<f32>,
     speed: Vector2D<f32>,
     size: f32,
     fix: bool,
@@ -20,6 +68,7 @@ impl Particle {
     fn new(x: f32, y: f32, size: f32) -> Self {
         Self {
             pos: Vector2D::new(x, y),
+            prev: Vector2D::new(x, y),
             speed: Vector2D { x: 0.0, y: 0.0 },
             size,
             fix: false,
@@ -36,7 +85,7 @@ impl Particle {
     //     self.speed.y = 0.0;
     // }
 
-    fn apply_gravity(&mut self, anchor: Vector2D<f32>, dt: f32) {
+    fn apply_gravity(&mut self, anchor: Vector2D<f32>, params: &ParticleParams, dt: f32) {
         if !self.fix {
             // simulate simple gravity
             let dir = Vector2D {
@@ -46,7 +95,7 @@ impl Particle {
 
             let dist_sq: f32 = dir.x * dir.x + dir.y * dir.y;
 
-            let a = 1000.0;
+            let a = params.pull;
             if dist_sq != 0.0 {
                 let dist = dist_sq.sqrt();
                 let normale = Vector2D {
@@ -62,12 +111,11 @@ impl Particle {
             }
         }
 
-        let damping = 0.95;
-        self.speed.x *= damping;
-        self.speed.y *= damping;
+        self.speed.x *= params.damping;
+        self.speed.y *= params.damping;
     }
 
-    fn resolve_collision(&mut self, other: &mut Particle) {
+    fn resolve_collision(&mut self, other: &mut Particle, restitution: f32) {
         if !self.fix {
             let dir = Vector2D {
                 x: other.pos.x - self.pos.x,
@@ -107,7 +155,7 @@ impl Particle {
             }
 
             if !other.fix {
-                let e = 0.5;
+                let e = restitution;
 
                 let k = (1.0 + e) * 0.5 * normale_relative_speed;
 
@@ -149,28 +197,43 @@ impl Particle {
         }
     }
 
-    fn update(&mut self, anchor: Vector2D<f32>, dt: f32) {
-        self.apply_gravity(anchor, dt);
+    fn save(&self, record: &mut Record) {
+        record.push_vector(self.pos);
+        record.push_vector(self.speed);
+        record.push_f32(self.size);
+        record.push_bool(self.fix);
+    }
+
+    fn restore(record: &mut Record) -> Result<Self, SnapshotError> {
+        let pos = record.read_vector()?;
+        Ok(Self {
+            pos,
+            prev: pos,
+            speed: record.read_vector()?,
+            size: record.read_f32()?,
+            fix: record.read_bool()?,
+        })
+    }
+
+    fn update(&mut self, anchor: Vector2D<f32>, params: &ParticleParams, dt: f32) {
+        self.prev = self.pos;
+        self.apply_gravity(anchor, params, dt);
         self.pos.x += self.speed.x * dt;
         self.pos.y += self.speed.y * dt;
     }
 }
 
 impl Drawable for Particle {
-    fn draw(&self, frame: &mut Frame) {
-        if self.pos.x >= 0.0
-            && self.pos.y >= 0.0
-            && (self.pos.x as usize) < frame.width
-            && (self.pos.y as usize) < frame.height
-        {
-            if self.fix {
-                frame.buffer[self.pos.y as usize * frame.width + self.pos.x as usize] =
-                    rgb!(255, 0, 0);
-            } else {
-                frame.buffer[self.pos.y as usize * frame.width + self.pos.x as usize] =
-                    rgb!(255, 255, 0);
-            }
-        }
+    fn draw(&self, frame: &mut Frame, view: &View) {
+        let pos = view
+            .camera
+            .world_to_screen(self.prev.lerp(self.pos, view.alpha));
+        let color = if self.fix {
+            Color::rgb(255, 0, 0)
+        } else {
+            Color::rgba(255, 220, 0, 120)
+        };
+        frame.fill_circle(pos, self.size * view.camera.zoom, color);
     }
 }
 
@@ -179,6 +242,9 @@ pub struct ParticleSystem {
     anchor: Particle,
     // pinch: Option<usize>,
     grid: SpatialGrid,
+    params: ParticleParams,
+    render: RenderMode,
+    metaballs: Metaballs,
 }
 
 impl ParticleSystem {
@@ -188,31 +254,31 @@ impl ParticleSystem {
         mut cell_nb: usize,
         cell_size: f32,
     ) -> Self {
-        // create cell all around the anchor pos
+        // one cell per unit all around the anchor pos, in a square holding
+        // the anchor and `cell_nb - 1` particles
         cell_nb -= 1;
-        let mut cells: Vec<Particle> = Vec::new();
-        let radius = (cell_nb as f32).sqrt().ceil() / 2.0;
-        let mut cell_count = 0;
-        for y in (anchor.y - radius * cell_size) as usize..(anchor.y + radius * cell_size) as usize
-        {
-            for x in
-                (anchor.x - radius * cell_size) as usize..(anchor.x + radius * cell_size) as usize
-            {
-                if x == anchor.x.floor() as usize && y == anchor.y.floor() as usize {
-                    continue;
-                } else if cell_count < cell_nb {
+        let mut cells: Vec<Particle> = Vec::with_capacity(cell_nb);
+        let side = ((cell_nb + 1) as f32).sqrt().ceil() as isize;
+        let origin = Vector2D {
+            x: (anchor.x - side as f32 / 2.0).floor() as isize,
+            y: (anchor.y - side as f32 / 2.0).floor() as isize,
+        };
+        let anchor_tile = Vector2D {
+            x: anchor.x.floor() as isize,
+            y: anchor.y.floor() as isize,
+        };
+        'fill: for y in origin.y..origin.y + side {
+            for x in origin.x..origin.x + side {
+                if cells.len() >= cell_nb {
+                    break 'fill;
+                }
+                if x != anchor_tile.x || y != anchor_tile.y {
                     cells.push(Particle::new(x as f32, y as f32, cell_size));
-                    cell_count += 1;
-                } else {
-                    break;
                 }
             }
-            if cell_count >= cell_nb {
-                break;
-            }
         }
 
-        Self {
+        let mut system = Self {
             cells,
             anchor: Particle::new(anchor.x, anchor.y, cell_size).with_fix(),
             // pinch: None,
@@ -224,18 +290,75 @@ impl ParticleSystem {
                     y: Y_HASH,
                 },
             ),
+            params: ParticleParams::default(),
+            render: RenderMode::Metaballs,
+            metaballs: Metaballs::new(METABALL_RADIUS),
+        };
+        system.refresh_metaballs();
+        system
+    }
+
+    /// move every particle by up to `JITTER` off its spawn lattice, runs
+    /// with the same seed start from the same uneven layout
+    pub fn with_jitter(mut self, seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        for cell in &mut self.cells {
+            cell.pos = cell.pos.add(Vector2D {
+                x: rng.range(-JITTER, JITTER),
+                y: rng.range(-JITTER, JITTER),
+            });
+            cell.prev = cell.pos;
         }
+        self.refresh_metaballs();
+        self
+    }
+
+    pub fn with_params(mut self, params: ParticleParams) -> Self {
+        self.params = params;
+        self
+    }
+
+    pub fn params(&self) -> ParticleParams {
+        self.params
+    }
+
+    pub fn set_params(&mut self, params: ParticleParams) {
+        self.params = params;
+    }
+
+    /// number of particles, the anchor excluded
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = Vector2D<f32>> + '_ {
+        self.cells.iter().map(|cell| cell.pos)
+    }
+
+    /// point every particle is pulled towards
+    pub fn anchor(&self) -> Vector2D<f32> {
+        self.anchor.pos
+    }
+
+    pub fn set_anchor(&mut self, pos: Vector2D<f32>) {
+        self.anchor.pos = pos;
+        self.anchor.prev = pos;
+    }
+
+    fn refresh_metaballs(&mut self) {
+        let points: Vec<_> = self.cells.iter().map(|cell| cell.pos).collect();
+        self.metaballs.update(&points);
     }
 
     fn valid_cell(&self, index: usize) -> bool {
         if index == 0 {
             true
         } else {
-            if let Some(_) = self.cells.get(index - 1) {
-                true
-            } else {
-                false
-            }
+            self.cells.get(index - 1).is_some()
         }
     }
 
@@ -266,31 +389,15 @@ impl ParticleSystem {
             if index1 >= index2 {
                 let (left, right) = self.cells.split_at_mut(index1);
 
-                let cell1 = if let Some(cell1) = right.get_mut(0) {
-                    cell1
-                } else {
-                    return None;
-                };
-                let cell2 = if let Some(cell2) = left.get_mut(index2) {
-                    cell2
-                } else {
-                    return None;
-                };
+                let cell1 = right.get_mut(0)?;
+                let cell2 = left.get_mut(index2)?;
 
                 Some((cell1, cell2))
             } else {
                 let (left, right) = self.cells.split_at_mut(index2);
 
-                let cell1 = if let Some(cell1) = left.get_mut(index1) {
-                    cell1
-                } else {
-                    return None;
-                };
-                let cell2 = if let Some(cell2) = right.get_mut(0) {
-                    cell2
-                } else {
-                    return None;
-                };
+                let cell1 = left.get_mut(index1)?;
+                let cell2 = right.get_mut(0)?;
 
                 Some((cell1, cell2))
             }
@@ -314,12 +421,20 @@ impl ParticleSystem {
 }
 
 impl Inputable for ParticleSystem {
-    fn handle_input(&mut self, input: Input) {
-        if input.mouse.right {
-            self.anchor.pos = Vector2D {
-                x: input.mouse.pos.x,
-                y: input.mouse.pos.y,
+    fn handle_input(&mut self, input: &Input) {
+        if input.action("particles_render").pressed {
+            self.render = match self.render {
+                RenderMode::Points => RenderMode::Metaballs,
+                RenderMode::Metaballs => RenderMode::Points,
             };
+        }
+
+        if input.action("move_anchor").held {
+            self.set_anchor(input.mouse.world_pos);
+
+            // scrolling while moving the anchor tunes its pull
+            self.params.pull =
+                (self.params.pull * PULL_STEP.powf(input.mouse.wheel.y)).clamp(MIN_PULL, MAX_PULL);
 
             // match self.pinch_cell() {
             //     Some(cell) => {}
@@ -327,20 +442,36 @@ impl Inputable for ParticleSystem {
             //         // self.set_pinch(Some(input.mouse.pos.0, input.mouse.pos.1));
             //     }
             // }
-        } else {
         }
+
+        let mut nudge = Vector2D { x: 0.0, y: 0.0 };
+        for (action, dir) in [
+            ("nudge_left", Vector2D { x: -1.0, y: 0.0 }),
+            ("nudge_right", Vector2D { x: 1.0, y: 0.0 }),
+            ("nudge_up", Vector2D { x: 0.0, y: -1.0 }),
+            ("nudge_down", Vector2D { x: 0.0, y: 1.0 }),
+        ] {
+            if input.action(action).held {
+                nudge = nudge.add(dir);
+            }
+        }
+        if input.action("nudge_fast").held {
+            nudge = nudge.vmul(4.0);
+        }
+        self.anchor.pos = self.anchor.pos.add(nudge.vmul(ANCHOR_NUDGE));
     }
 }
 
 impl Updatable for ParticleSystem {
-    fn update(&mut self, dt: f32) {
+    fn update(&mut self, dt: f32, _commands: &mut Commands) {
         self.grid.clear();
-        self.anchor.update(self.anchor.pos, dt);
+        self.anchor.update(self.anchor.pos, &self.params, dt);
         self.grid.push(0, self.anchor.pos, self.anchor.size);
         for (i, cell) in &mut self.cells.iter_mut().enumerate() {
-            cell.update(self.anchor.pos, dt);
+            cell.update(self.anchor.pos, &self.params, dt);
             self.grid.push(i + 1, cell.pos, cell.size);
         }
+        let restitution = self.params.restitution;
         for i in 0..self.cells.len() {
             let collisions = self.grid.get(self.cells[i].pos, self.cells[i].size);
             for collision in collisions {
@@ -354,19 +485,124 @@ impl Updatable for ParticleSystem {
 
                 let (cell, other) = self.get_two_cell(i + 1, collision).unwrap();
 
-                cell.resolve_collision(other);
+                cell.resolve_collision(other, restitution);
             }
         }
+        self.refresh_metaballs();
     }
 }
 
 impl Drawable for ParticleSystem {
-    fn draw(&self, frame: &mut Frame) {
+    fn draw(&self, frame: &mut Frame, view: &View) {
+        match self.render {
+            RenderMode::Points => {
+                // overlapping particles add up, dense regions glow
+                let mode = frame.blend_mode();
+                frame.set_blend_mode(BlendMode::Add);
+                for cell in &self.cells {
+                    cell.draw(frame, view);
+                }
+                frame.set_blend_mode(mode);
+            }
+            RenderMode::Metaballs => {
+                let points: Vec<_> = self
+                    .cells
+                    .iter()
+                    .map(|cell| cell.prev.lerp(cell.pos, view.alpha))
+                    .collect();
+                self.metaballs.draw(frame, &view.camera, &points);
+            }
+        }
+        self.anchor.draw(frame, view);
+        let anchor = view
+            .camera
+            .world_to_screen(self.anchor.prev.lerp(self.anchor.pos, view.alpha));
+        frame.circle(anchor, 6.0, rgb!(255, 0, 0));
+    }
+
+    fn draw_debug(&self, frame: &mut Frame, view: &View) {
+        debug::draw_tiles(
+            frame,
+            view,
+            &self.grid,
+            self.cells.iter().map(|cell| cell.pos),
+        );
+
         for cell in &self.cells {
-            cell.draw(frame);
+            let pos = cell.prev.lerp(cell.pos, view.alpha);
+            frame.line(
+                view.camera.world_to_screen(pos),
+                view.camera
+                    .world_to_screen(pos.add(cell.speed.vmul(VELOCITY_SCALE))),
+                Color::rgb(0, 200, 255),
+            );
         }
-        self.anchor.draw(frame);
+
+        let anchor = self.anchor.prev.lerp(self.anchor.pos, view.alpha);
+        debug::draw_marker(
+            frame,
+            view.camera.world_to_screen(anchor),
+            8.0,
+            Color::rgb(255, 80, 80),
+        );
     }
 }
 
-impl Entity for ParticleSystem {}
+impl Snapshotable for ParticleSystem {
+    fn save(&self) -> Record {
+        let mut record = Record::new("particles");
+        record.push_f32(self.params.pull);
+        record.push_f32(self.params.damping);
+        record.push_f32(self.params.restitution);
+        self.anchor.save(&mut record);
+        record.push_usize(self.cells.len());
+        for cell in &self.cells {
+            cell.save(&mut record);
+        }
+        record
+    }
+
+    fn restore(&self, record: &mut Record) -> Result<Restore, SnapshotError> {
+        record.expect_kind("particles")?;
+        let params = ParticleParams {
+            pull: record.read_f32()?,
+            damping: record.read_f32()?,
+            restitution: record.read_f32()?,
+        };
+        let anchor = Particle::restore(record)?;
+        let cell_nb = record.read_len()?;
+        let mut cells = Vec::with_capacity(cell_nb);
+        for _ in 0..cell_nb {
+            cells.push(Particle::restore(record)?);
+        }
+        Ok(restore_with(move |system: &mut Self| {
+            system.params = params;
+            system.anchor = anchor;
+            system.cells = cells;
+            system.refresh_metaballs();
+        }))
+    }
+}
+
+impl Entity for ParticleSystem {
+    fn name(&self) -> &'static str {
+        "particles"
+    }
+
+    fn inspect(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("particles", self.cells.len().to_string()),
+            ("pull", format!("{:.0}", self.params.pull)),
+            ("render", format!("{:?}", self.render).to_lowercase()),
+        ]
+    }
+
+    fn params_mut(&mut self) -> Vec<Param<'_>> {
+        let params = &mut self.params;
+        vec![
+            Param::float("pull", &mut params.pull, MIN_PULL, MAX_PULL),
+            Param::float("damping", &mut params.damping, 0.8, 1.0),
+            Param::float("restitution", &mut params.restitution, 0.0, 1.0),
+        ]
+    }
+}
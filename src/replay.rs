@@ -0,0 +1,185 @@
+use crate::input::InputState;
+use crate::key::KeySet;
+use crate::vector::Vector2D;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+pub const VERSION: u16 = 3;
+
+const MAGIC: &[u8; 8] = b"SLIMEREC";
+
+const HAS_POS: u8 = 1 << 0;
+const HAS_ABS_POS: u8 = 1 << 1;
+const LEFT: u8 = 1 << 2;
+const MIDDLE: u8 = 1 << 3;
+const RIGHT: u8 = 1 << 4;
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+fn encode(dt: f32, state: &InputState, out: &mut Vec<u8>) {
+    let mut flags = 0;
+    if state.mouse_pos.is_some() {
+        flags |= HAS_POS;
+    }
+    if state.mouse_abs_pos.is_some() {
+        flags |= HAS_ABS_POS;
+    }
+    if state.mouse_left {
+        flags |= LEFT;
+    }
+    if state.mouse_middle {
+        flags |= MIDDLE;
+    }
+    if state.mouse_right {
+        flags |= RIGHT;
+    }
+
+    out.extend_from_slice(&dt.to_le_bytes());
+    out.push(flags);
+    out.extend_from_slice(&state.keys.bits().to_le_bytes());
+    out.extend_from_slice(&state.mouse_wheel.x.to_le_bytes());
+    out.extend_from_slice(&state.mouse_wheel.y.to_le_bytes());
+    for pos in [state.mouse_pos, state.mouse_abs_pos].into_iter().flatten() {
+        out.extend_from_slice(&pos.x.to_le_bytes());
+        out.extend_from_slice(&pos.y.to_le_bytes());
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn is_empty(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    fn take<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + N)
+            .ok_or_else(|| invalid("truncated input recording"))?;
+        self.pos += N;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn f32(&mut self) -> io::Result<f32> {
+        Ok(f32::from_le_bytes(self.take()?))
+    }
+
+    fn vector(&mut self) -> io::Result<Vector2D<f32>> {
+        Ok(Vector2D {
+            x: self.f32()?,
+            y: self.f32()?,
+        })
+    }
+
+    fn frame(&mut self) -> io::Result<(f32, InputState)> {
+        let dt = self.f32()?;
+        let [flags] = self.take()?;
+        let keys = KeySet::from_bits(u128::from_le_bytes(self.take()?));
+        let mouse_wheel = self.vector()?;
+        let mouse_pos = if flags & HAS_POS != 0 {
+            Some(self.vector()?)
+        } else {
+            None
+        };
+        let mouse_abs_pos = if flags & HAS_ABS_POS != 0 {
+            Some(self.vector()?)
+        } else {
+            None
+        };
+        Ok((
+            dt,
+            InputState {
+                mouse_pos,
+                mouse_abs_pos,
+                mouse_left: flags & LEFT != 0,
+                mouse_middle: flags & MIDDLE != 0,
+                mouse_right: flags & RIGHT != 0,
+                mouse_wheel,
+                keys,
+            },
+        ))
+    }
+}
+
+/// writes the polled input of every frame together with its dt
+pub struct InputRecorder {
+    file: BufWriter<File>,
+    buffer: Vec<u8>,
+}
+
+impl InputRecorder {
+    /// `step` is the physics step of the recording core, replays check it
+    pub fn create(path: &Path, step: f32) -> io::Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(MAGIC)?;
+        file.write_all(&VERSION.to_le_bytes())?;
+        file.write_all(&step.to_le_bytes())?;
+        Ok(Self {
+            file,
+            buffer: Vec::new(),
+        })
+    }
+
+    pub fn record(&mut self, dt: f32, state: &InputState) -> io::Result<()> {
+        self.buffer.clear();
+        encode(dt, state, &mut self.buffer);
+        self.file.write_all(&self.buffer)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// frames of an `InputRecorder` file, fed back one per frame
+pub struct InputReplay {
+    step: f32,
+    frames: Vec<(f32, InputState)>,
+    cursor: usize,
+}
+
+impl InputReplay {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let mut reader = Reader {
+            bytes: &bytes,
+            pos: 0,
+        };
+        if &reader.take::<8>()? != MAGIC {
+            return Err(invalid("not an input recording"));
+        }
+        let version = u16::from_le_bytes(reader.take()?);
+        if version != VERSION {
+            return Err(invalid(format!("unsupported recording version {version}")));
+        }
+        let step = reader.f32()?;
+
+        let mut frames = Vec::new();
+        while !reader.is_empty() {
+            frames.push(reader.frame()?);
+        }
+        Ok(Self {
+            step,
+            frames,
+            cursor: 0,
+        })
+    }
+
+    /// physics step the session was recorded with
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    pub fn next_frame(&mut self) -> Option<(f32, InputState)> {
+        let frame = self.frames.get(self.cursor).copied();
+        self.cursor += 1;
+        frame
+    }
+}
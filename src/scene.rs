@@ -0,0 +1,1080 @@
+use crate::entities::{Entities, EntityId};
+use crate::entity::{Entity, Layer};
+use crate::param::ParamValue;
+use crate::particle::ParticleSystem;
+use crate::tortilla::Tortilla;
+use crate::vector::Vector2D;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const DEFAULT_TITLE: &str = "slime";
+const DEFAULT_WIDTH: usize = 800;
+const DEFAULT_HEIGHT: usize = 600;
+const DEFAULT_REFRESH: usize = 60;
+const DEFAULT_PHYSICS: usize = 120;
+const DEFAULT_MAX_STEPS: usize = 8;
+/// seconds between two looks at a watched scene file
+const POLL_INTERVAL: f32 = 0.5;
+/// the tortilla grows with the square of its radius, past this it takes
+/// seconds to build and cannot run at frame rate
+pub const MAX_RADIUS: f32 = 200.0;
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    Parse {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(err) => write!(f, "{err}"),
+            SceneError::Parse {
+                line,
+                column,
+                message,
+            } if *line == 0 => write!(f, "{message} (set on the command line)"),
+            SceneError::Parse {
+                line,
+                column,
+                message,
+            } => write!(f, "line {line}, column {column}: {message}"),
+        }
+    }
+}
+
+impl From<io::Error> for SceneError {
+    fn from(err: io::Error) -> Self {
+        SceneError::Io(err)
+    }
+}
+
+fn parse_error<T>(line: usize, column: usize, message: impl Into<String>) -> Result<T, SceneError> {
+    Err(SceneError::Parse {
+        line,
+        column,
+        message: message.into(),
+    })
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Float(f32),
+    Bool(bool),
+    Str(String),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::Int(_) => "integer",
+            Value::Float(_) => "float",
+            Value::Bool(_) => "bool",
+            Value::Str(_) => "string",
+            Value::Array(_) => "array",
+        }
+    }
+}
+
+/// `key = value` line of a table
+#[derive(Clone, Debug)]
+pub struct Field {
+    pub key: String,
+    pub value: Value,
+    pub line: usize,
+    /// column of the value, errors about it point there
+    pub column: usize,
+}
+
+impl Field {
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, SceneError> {
+        parse_error(self.line, self.column, message)
+    }
+
+    fn expected<T>(&self, what: &str) -> Result<T, SceneError> {
+        self.error(format!(
+            "`{}` expects {what}, found {}",
+            self.key,
+            self.value.type_name()
+        ))
+    }
+
+    fn as_f32(&self) -> Result<f32, SceneError> {
+        match self.value {
+            Value::Int(value) => Ok(value as f32),
+            Value::Float(value) => Ok(value),
+            _ => self.expected("a number"),
+        }
+    }
+
+    fn as_usize(&self) -> Result<usize, SceneError> {
+        match self.value {
+            Value::Int(value) if value >= 0 => Ok(value as usize),
+            Value::Int(_) => self.error(format!("`{}` cannot be negative", self.key)),
+            _ => self.expected("an integer"),
+        }
+    }
+
+    fn as_bool(&self) -> Result<bool, SceneError> {
+        match self.value {
+            Value::Bool(value) => Ok(value),
+            _ => self.expected("true or false"),
+        }
+    }
+
+    fn as_str(&self) -> Result<&str, SceneError> {
+        match &self.value {
+            Value::Str(value) => Ok(value),
+            _ => self.expected("a string"),
+        }
+    }
+
+    fn as_vector(&self) -> Result<Vector2D<f32>, SceneError> {
+        let number = |value: &Value| match *value {
+            Value::Int(value) => Some(value as f32),
+            Value::Float(value) => Some(value),
+            _ => None,
+        };
+        match &self.value {
+            Value::Array(values) if values.len() == 2 => {
+                match (number(&values[0]), number(&values[1])) {
+                    (Some(x), Some(y)) => Ok(Vector2D { x, y }),
+                    _ => self.error(format!("`{}` expects two numbers", self.key)),
+                }
+            }
+            _ => self.expected("an [x, y] array"),
+        }
+    }
+}
+
+/// `[name]` or `[[name]]` header and the fields below it
+#[derive(Clone, Debug)]
+struct Table {
+    name: String,
+    line: usize,
+    column: usize,
+    fields: Vec<Field>,
+}
+
+impl Table {
+    fn get(&self, key: &str) -> Option<&Field> {
+        self.fields.iter().find(|field| field.key == key)
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, SceneError> {
+        parse_error(self.line, self.column, message)
+    }
+}
+
+/// reads one value, tracking the column for errors
+struct Cursor<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    /// column of `chars[0]`
+    start: usize,
+    text: &'a str,
+}
+
+impl Cursor<'_> {
+    fn column(&self) -> usize {
+        self.start + self.pos
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, SceneError> {
+        parse_error(self.line, self.column(), message)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, SceneError> {
+        self.skip_spaces();
+        match self.peek() {
+            None => self.error("missing value"),
+            Some('"') => self.string(),
+            Some('[') => self.array(),
+            Some(c) if c.is_ascii_alphabetic() => {
+                let word = self.word();
+                match word.as_str() {
+                    "true" => Ok(Value::Bool(true)),
+                    "false" => Ok(Value::Bool(false)),
+                    _ => parse_error(
+                        self.line,
+                        self.column() - word.len(),
+                        format!("unknown value `{word}`"),
+                    ),
+                }
+            }
+            Some(_) => self.number(),
+        }
+    }
+
+    fn word(&mut self) -> String {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || "_.+-".contains(c))
+        {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    fn number(&mut self) -> Result<Value, SceneError> {
+        let column = self.column();
+        let word = self.word();
+        if word.is_empty() {
+            return self.error(format!("unexpected `{}`", self.peek().unwrap_or(' ')));
+        }
+        let is_float = word.contains(['.', 'e', 'E']);
+        let value = if is_float {
+            word.parse().ok().map(Value::Float)
+        } else {
+            word.parse().ok().map(Value::Int)
+        };
+        match value {
+            Some(value) => Ok(value),
+            None => parse_error(self.line, column, format!("invalid number `{word}`")),
+        }
+    }
+
+    fn string(&mut self) -> Result<Value, SceneError> {
+        let column = self.column();
+        self.pos += 1;
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                None => return parse_error(self.line, column, "unterminated string"),
+                Some('"') => break,
+                Some('\\') => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some(c @ ('"' | '\\')) => value.push(c),
+                        Some('n') => value.push('\n'),
+                        _ => return self.error("unknown escape"),
+                    }
+                }
+                Some(c) => value.push(c),
+            }
+            self.pos += 1;
+        }
+        self.pos += 1;
+        Ok(Value::Str(value))
+    }
+
+    fn array(&mut self) -> Result<Value, SceneError> {
+        let column = self.column();
+        self.pos += 1;
+        let mut values = Vec::new();
+        loop {
+            self.skip_spaces();
+            match self.peek() {
+                None => return parse_error(self.line, column, "unterminated array"),
+                Some(']') => break,
+                _ => values.push(self.value()?),
+            }
+            self.skip_spaces();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(']') => break,
+                None => return parse_error(self.line, column, "unterminated array"),
+                Some(c) => return self.error(format!("expected `,` or `]`, found `{c}`")),
+            }
+        }
+        self.pos += 1;
+        Ok(Value::Array(values))
+    }
+
+    /// the whole value was read, only a comment may follow
+    fn end(&mut self) -> Result<(), SceneError> {
+        self.skip_spaces();
+        match self.peek() {
+            None | Some('#') => Ok(()),
+            Some(_) => self.error(format!(
+                "unexpected `{}` after value",
+                self.text.chars().skip(self.pos).collect::<String>().trim()
+            )),
+        }
+    }
+}
+
+fn is_name(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// tables in file order, `window` first if present
+fn parse_tables(text: &str) -> Result<Vec<Table>, SceneError> {
+    let mut tables: Vec<Table> = Vec::new();
+    let mut seen_window = false;
+    for (i, raw) in text.lines().enumerate() {
+        let line = i + 1;
+        let indent = raw.len() - raw.trim_start().len();
+        let content = raw.trim();
+        if content.is_empty() || content.starts_with('#') {
+            continue;
+        }
+        let column = indent + 1;
+
+        if content.starts_with('[') {
+            let content = content.split('#').next().unwrap_or("").trim_end();
+            let (array, name) = if let Some(inner) = content
+                .strip_prefix("[[")
+                .and_then(|rest| rest.strip_suffix("]]"))
+            {
+                (true, inner.trim())
+            } else if let Some(inner) = content
+                .strip_prefix('[')
+                .and_then(|rest| rest.strip_suffix(']'))
+            {
+                (false, inner.trim())
+            } else {
+                return parse_error(line, column, "unclosed table header");
+            };
+            if !is_name(name) {
+                return parse_error(line, column + 1, format!("invalid table name `{name}`"));
+            }
+            match (array, name) {
+                (false, "window") if seen_window => {
+                    return parse_error(line, column, "duplicate [window] table");
+                }
+                (false, "window") => seen_window = true,
+                (false, _) => {
+                    return parse_error(
+                        line,
+                        column,
+                        format!("unknown table [{name}], entities use [[{name}]]"),
+                    );
+                }
+                (true, _) => {}
+            }
+            tables.push(Table {
+                name: name.to_string(),
+                line,
+                column,
+                fields: Vec::new(),
+            });
+            continue;
+        }
+
+        let Some((key, value)) = raw.split_once('=') else {
+            return parse_error(
+                line,
+                column,
+                format!("expected `key = value`, found `{content}`"),
+            );
+        };
+        let key_text = key.trim();
+        if !is_name(key_text) {
+            return parse_error(line, column, format!("invalid key `{key_text}`"));
+        }
+        let Some(table) = tables.last_mut() else {
+            return parse_error(line, column, "field outside of a table");
+        };
+        if table.get(key_text).is_some() {
+            return parse_error(line, column, format!("duplicate key `{key_text}`"));
+        }
+
+        let value_start = key.chars().count() + 1;
+        let mut cursor = Cursor {
+            chars: value.chars().collect(),
+            pos: 0,
+            line,
+            start: value_start + 1,
+            text: value,
+        };
+        let parsed = cursor.value()?;
+        cursor.end()?;
+        let value_column = cursor.start + value.chars().take_while(|c| c.is_whitespace()).count();
+        table.fields.push(Field {
+            key: key_text.to_string(),
+            value: parsed,
+            line,
+            column: value_column,
+        });
+    }
+    Ok(tables)
+}
+
+/// `[window]` table
+#[derive(Clone, Debug, PartialEq)]
+pub struct WindowConfig {
+    pub title: String,
+    pub width: usize,
+    pub height: usize,
+    pub refresh: usize,
+    pub physics: usize,
+    pub max_steps: usize,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            title: DEFAULT_TITLE.to_string(),
+            width: DEFAULT_WIDTH,
+            height: DEFAULT_HEIGHT,
+            refresh: DEFAULT_REFRESH,
+            physics: DEFAULT_PHYSICS,
+            max_steps: DEFAULT_MAX_STEPS,
+        }
+    }
+}
+
+impl WindowConfig {
+    fn from_table(table: &Table) -> Result<Self, SceneError> {
+        let mut config = WindowConfig::default();
+        for field in &table.fields {
+            match field.key.as_str() {
+                "title" => config.title = field.as_str()?.to_string(),
+                "width" => config.width = field.as_usize()?,
+                "height" => config.height = field.as_usize()?,
+                "refresh" => config.refresh = field.as_usize()?,
+                "physics" => config.physics = field.as_usize()?,
+                "max_steps" => config.max_steps = field.as_usize()?,
+                key => return field.error(format!("unknown window field `{key}`")),
+            }
+            let positive = !matches!(field.value, Value::Int(0));
+            if !positive && field.key != "title" {
+                return field.error(format!("`{}` must be positive", field.key));
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// one `[[kind]]` table: constructor fields, and parameters set by name
+/// through `Entity::params_mut` once the entity is built
+#[derive(Clone, Debug)]
+pub struct EntitySpec {
+    pub kind: String,
+    table: Table,
+}
+
+impl EntitySpec {
+    /// fields read by the constructor of `kind`, the others are parameters
+    fn constructor_keys(&self) -> &'static [&'static str] {
+        match self.kind.as_str() {
+            "tortilla" => &["center", "cell_size", "rigidity", "radius"],
+            "particles" => &["anchor", "count", "cell_size", "seed"],
+            _ => &[],
+        }
+    }
+
+    /// `default` when missing, else above zero and at most `max`
+    fn f32_within(&self, key: &str, default: f32, max: f32) -> Result<f32, SceneError> {
+        let Some(field) = self.table.get(key) else {
+            return Ok(default);
+        };
+        let number = field.as_f32()?;
+        if !(number > 0.0 && number.is_finite()) {
+            return field.error(format!("`{key}` must be positive"));
+        }
+        if number > max {
+            return field.error(format!("`{key}` must be at most {max}"));
+        }
+        Ok(number)
+    }
+
+    fn usize_or(&self, key: &str, default: usize) -> Result<usize, SceneError> {
+        self.table.get(key).map_or(Ok(default), Field::as_usize)
+    }
+
+    fn vector_or(&self, key: &str, default: Vector2D<f32>) -> Result<Vector2D<f32>, SceneError> {
+        self.table.get(key).map_or(Ok(default), Field::as_vector)
+    }
+
+    /// `grid_size` is the world area covered by the spatial grids
+    pub fn build(&self, grid_size: Vector2D<usize>) -> Result<Box<dyn Entity>, SceneError> {
+        let mut entity: Box<dyn Entity> = match self.kind.as_str() {
+            "tortilla" => Box::new(Tortilla::new(
+                grid_size,
+                self.vector_or("center", Vector2D::new(200.0, 300.0))?,
+                self.f32_within("cell_size", 0.5, f32::MAX)?,
+                self.f32_within("rigidity", 3.0, f32::MAX)?,
+                10,
+                self.f32_within("radius", 20.0, MAX_RADIUS)?,
+            )),
+            "particles" => {
+                let count = self.usize_or("count", 1600)?;
+                if count == 0 {
+                    return self
+                        .table
+                        .get("count")
+                        .unwrap()
+                        .error("`count` must be positive");
+                }
+                let system = ParticleSystem::new(
+                    grid_size,
+                    self.vector_or("anchor", Vector2D::new(600.0, 300.0))?,
+                    count,
+                    self.f32_within("cell_size", 0.5, f32::MAX)?,
+                );
+                match self.table.get("seed") {
+                    Some(seed) => Box::new(system.with_jitter(seed.as_usize()? as u64)),
+                    None => Box::new(system),
+                }
+            }
+            kind => return self.table.error(format!("unknown entity kind `{kind}`")),
+        };
+        self.apply_params(entity.as_mut())?;
+        Ok(entity)
+    }
+
+    /// `layer` field, else the default layer of `entity`
+    fn layer_for(&self, entity: &dyn Entity) -> Result<Layer, SceneError> {
+        let Some(field) = self.table.get("layer") else {
+            return Ok(entity.layer());
+        };
+        let name = field.as_str()?;
+        match Layer::from_name(name) {
+            Some(layer) => Ok(layer),
+            None => field.error(format!(
+                "unknown layer `{name}`, expected background, world, overlay or ui"
+            )),
+        }
+    }
+
+    /// constructor fields are equal, parameters alone can be applied live
+    fn same_structure(&self, other: &EntitySpec) -> bool {
+        self.kind == other.kind
+            && self.constructor_keys().iter().all(|key| {
+                self.table.get(key).map(|field| &field.value)
+                    == other.table.get(key).map(|field| &field.value)
+            })
+    }
+
+    /// set every non constructor field on the matching parameter,
+    /// nothing is changed unless all of them are valid
+    pub fn apply_params(&self, entity: &mut dyn Entity) -> Result<(), SceneError> {
+        let settings = self.settings(entity)?;
+        write_settings(entity, settings);
+        Ok(())
+    }
+
+    /// check every non constructor field against the matching parameter
+    /// without writing any
+    fn settings(&self, entity: &mut dyn Entity) -> Result<Vec<(usize, Setting)>, SceneError> {
+        let constructor = self.constructor_keys();
+        let params = entity.params_mut();
+        let mut settings = Vec::new();
+        for field in &self.table.fields {
+            if constructor.contains(&field.key.as_str()) || field.key == "layer" {
+                continue;
+            }
+            let Some(index) = params.iter().position(|param| param.name == field.key) else {
+                return field.error(format!("unknown field `{}` for {}", field.key, self.kind));
+            };
+            let setting = match params[index].value {
+                ParamValue::Float { min, max, .. } => {
+                    let number = field.as_f32()?;
+                    if number < min || number > max {
+                        return field.error(format!("`{}` must be within {min}..{max}", field.key));
+                    }
+                    Setting::Float(number)
+                }
+                ParamValue::Int { min, max, .. } => {
+                    let number = field.as_usize()?;
+                    if number < min || number > max {
+                        return field.error(format!("`{}` must be within {min}..{max}", field.key));
+                    }
+                    Setting::Int(number)
+                }
+                ParamValue::Bool(_) => Setting::Bool(field.as_bool()?),
+            };
+            settings.push((index, setting));
+        }
+        Ok(settings)
+    }
+}
+
+/// write settings checked by `EntitySpec::settings` on the same entity
+fn write_settings(entity: &mut dyn Entity, settings: Vec<(usize, Setting)>) {
+    let mut params = entity.params_mut();
+    for (index, setting) in settings {
+        match (&mut params[index].value, setting) {
+            (ParamValue::Float { value, .. }, Setting::Float(number)) => **value = number,
+            (ParamValue::Int { value, .. }, Setting::Int(number)) => **value = number,
+            (ParamValue::Bool(value), Setting::Bool(flag)) => **value = flag,
+            _ => unreachable!("setting built from this parameter"),
+        }
+    }
+}
+
+/// validated value waiting to be written to a parameter
+enum Setting {
+    Float(f32),
+    Int(usize),
+    Bool(bool),
+}
+
+#[derive(Clone, Debug)]
+pub struct Scene {
+    pub window: WindowConfig,
+    pub entities: Vec<EntitySpec>,
+}
+
+/// field forced on every table of one name, whatever the file says
+#[derive(Clone, Debug)]
+pub struct Override {
+    pub table: &'static str,
+    pub key: &'static str,
+    pub value: Value,
+}
+
+impl Override {
+    pub fn new(table: &'static str, key: &'static str, value: Value) -> Self {
+        Self { table, key, value }
+    }
+
+    /// as a field, line 0 marks it as coming from outside the file
+    fn field(&self) -> Field {
+        Field {
+            key: self.key.to_string(),
+            value: self.value.clone(),
+            line: 0,
+            column: 0,
+        }
+    }
+}
+
+impl Scene {
+    /// text of the scene used when none is given on the command line
+    pub const DEFAULT: &str = include_str!("../scenes/default.scene");
+
+    /// parse then apply `overrides`, a `window` one adds the table if needed
+    pub fn parse(text: &str, overrides: &[Override]) -> Result<Self, SceneError> {
+        let mut tables = parse_tables(text)?;
+        for item in overrides {
+            if item.table == "window" && !tables.iter().any(|table| table.name == "window") {
+                tables.insert(
+                    0,
+                    Table {
+                        name: String::from("window"),
+                        line: 0,
+                        column: 0,
+                        fields: Vec::new(),
+                    },
+                );
+            }
+            for table in tables.iter_mut().filter(|table| table.name == item.table) {
+                table.fields.retain(|field| field.key != item.key);
+                table.fields.push(item.field());
+            }
+        }
+
+        let mut scene = Scene {
+            window: WindowConfig::default(),
+            entities: Vec::new(),
+        };
+        for table in tables {
+            if table.name == "window" {
+                scene.window = WindowConfig::from_table(&table)?;
+            } else {
+                scene.entities.push(EntitySpec {
+                    kind: table.name.clone(),
+                    table,
+                });
+            }
+        }
+        Ok(scene)
+    }
+
+    pub fn load(path: &Path, overrides: &[Override]) -> Result<Self, SceneError> {
+        Scene::parse(&fs::read_to_string(path)?, overrides)
+    }
+
+    fn grid_size(&self) -> Vector2D<usize> {
+        Vector2D {
+            x: self.window.width,
+            y: self.window.height,
+        }
+    }
+
+    /// build every entity of the scene with its parameters set and add
+    /// them on their layer, nothing is added unless all of them build
+    pub fn spawn(&self, entities: &mut Entities) -> Result<Vec<EntityId>, SceneError> {
+        let grid_size = self.grid_size();
+        let mut built = Vec::new();
+        for spec in &self.entities {
+            let entity = spec.build(grid_size)?;
+            built.push((spec.layer_for(entity.as_ref())?, entity));
+        }
+        Ok(built
+            .into_iter()
+            .map(|(layer, entity)| {
+                let id = entities.insert(entity);
+                entities.set_layer(id, layer);
+                id
+            })
+            .collect())
+    }
+}
+
+/// reloads a scene file when its modification time changes
+pub struct SceneWatcher {
+    path: PathBuf,
+    scene: Scene,
+    /// handle of the entity built from each spec of `scene`
+    ids: Vec<EntityId>,
+    overrides: Vec<Override>,
+    modified: Option<SystemTime>,
+    /// time left before the next look at the file
+    wait: f32,
+}
+
+impl SceneWatcher {
+    /// `scene` is the one loaded from `path`, `ids` the entities built
+    /// from it, in the same order
+    pub fn new(path: impl Into<PathBuf>, scene: Scene, ids: Vec<EntityId>) -> Self {
+        let path = path.into();
+        let modified = modified_time(&path);
+        Self {
+            path,
+            scene,
+            ids,
+            overrides: Vec::new(),
+            modified,
+            wait: POLL_INTERVAL,
+        }
+    }
+
+    /// applied again on every reload, as when the scene was first loaded
+    pub fn with_overrides(mut self, overrides: Vec<Override>) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// reload the file if it changed
+    pub fn poll(&mut self, dt: f32, entities: &mut Entities) {
+        self.wait -= dt;
+        if self.wait > 0.0 {
+            return;
+        }
+        self.wait = POLL_INTERVAL;
+
+        let modified = modified_time(&self.path);
+        if modified == self.modified {
+            return;
+        }
+        self.modified = modified;
+
+        let result =
+            Scene::load(&self.path, &self.overrides).and_then(|scene| self.apply(scene, entities));
+        let path = self.path.display();
+        match result {
+            Ok((kept, rebuilt)) => {
+                eprintln!("{path}: reloaded, {kept} updated, {rebuilt} rebuilt")
+            }
+            Err(err) => eprintln!("{path}: {err}"),
+        }
+    }
+
+    /// update entities whose constructor fields did not move, rebuild the
+    /// others; returns how many of each
+    fn apply(
+        &mut self,
+        scene: Scene,
+        entities: &mut Entities,
+    ) -> Result<(usize, usize), SceneError> {
+        if scene.window != self.scene.window {
+            eprintln!("{}: [window] changes apply on restart", self.path.display());
+        }
+
+        // the window cannot change, so neither does the area of the grids
+        let grid_size = self.scene.grid_size();
+        let mut kept = Vec::new();
+        let mut rebuilt = Vec::new();
+        for (i, spec) in scene.entities.iter().enumerate() {
+            // an entity removed since the last load is built again
+            let live = self.ids.get(i).is_some_and(|&id| entities.contains(id));
+            match self.scene.entities.get(i) {
+                Some(old) if live && old.same_structure(spec) => kept.push(i),
+                _ => {
+                    let entity = spec.build(grid_size)?;
+                    rebuilt.push((i, spec.layer_for(entity.as_ref())?, entity));
+                }
+            }
+        }
+        // every kept entity is checked before any of them changes
+        let mut updates = Vec::new();
+        for &i in &kept {
+            let id = self.ids[i];
+            if let Some(entity) = entities.get_mut(id) {
+                let spec = &scene.entities[i];
+                updates.push((id, spec.layer_for(entity)?, spec.settings(entity)?));
+            }
+        }
+        for (id, layer, settings) in updates {
+            if let Some(entity) = entities.get_mut(id) {
+                write_settings(entity, settings);
+            }
+            entities.set_layer(id, layer);
+        }
+        let counts = (kept.len(), rebuilt.len());
+
+        let new_count = scene.entities.len();
+        for id in self.ids.drain(new_count.min(self.ids.len())..) {
+            entities.remove(id);
+        }
+        for (i, layer, entity) in rebuilt {
+            let id = match self.ids.get(i) {
+                Some(&id) if entities.contains(id) => {
+                    entities.replace(id, entity);
+                    id
+                }
+                Some(_) => {
+                    self.ids[i] = entities.insert(entity);
+                    self.ids[i]
+                }
+                None => {
+                    self.ids.push(entities.insert(entity));
+                    self.ids[i]
+                }
+            };
+            entities.set_layer(id, layer);
+        }
+        self.scene = Scene {
+            window: self.scene.window.clone(),
+            ..scene
+        };
+        Ok(counts)
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// line, column and message of a parse error
+    fn error_at<T: fmt::Debug>(result: Result<T, SceneError>) -> (usize, usize, String) {
+        match result {
+            Err(SceneError::Parse {
+                line,
+                column,
+                message,
+            }) => (line, column, message),
+            other => panic!("expected a parse error, got {other:?}"),
+        }
+    }
+
+    fn parse_error_at(text: &str) -> (usize, usize, String) {
+        error_at(Scene::parse(text, &[]))
+    }
+
+    fn spawn_error_at(text: &str) -> (usize, usize, String) {
+        let scene = Scene::parse(text, &[]).unwrap();
+        error_at(scene.spawn(&mut Entities::default()))
+    }
+
+    #[test]
+    fn default_scene_parses() {
+        let scene = Scene::parse(Scene::DEFAULT, &[]).unwrap();
+        assert_eq!(scene.window, WindowConfig::default());
+        let kinds: Vec<_> = scene
+            .entities
+            .iter()
+            .map(|spec| spec.kind.as_str())
+            .collect();
+        assert_eq!(kinds, ["tortilla", "particles"]);
+    }
+
+    #[test]
+    fn values_parse() {
+        let text = "[window]\ntitle = \"a \\\"b\\\"\" # comment\nwidth = 640\n\n[[tortilla]]\ncenter = [ 1, -2.5e1 ]\nlayer = \"ui\"\noutline = false\n";
+        let scene = Scene::parse(text, &[]).unwrap();
+        assert_eq!(scene.window.title, "a \"b\"");
+        assert_eq!(scene.window.width, 640);
+        let spec = &scene.entities[0];
+        assert_eq!(
+            spec.table.get("center").unwrap().value,
+            Value::Array(vec![Value::Int(1), Value::Float(-25.0)])
+        );
+        assert_eq!(spec.table.get("outline").unwrap().value, Value::Bool(false));
+    }
+
+    #[test]
+    fn value_errors_point_at_the_value() {
+        let cases = [
+            ("[window]\nwidth = abc", 2, 9, "unknown value `abc`"),
+            ("[window]\nwidth = 1.5x", 2, 9, "invalid number `1.5x`"),
+            ("[window]\ntitle = \"abc", 2, 9, "unterminated string"),
+            ("[window]\ntitle = \"a\\q\"", 2, 12, "unknown escape"),
+            ("[window]\nwidth =", 2, 8, "missing value"),
+            (
+                "[window]\nwidth = 10 20",
+                2,
+                12,
+                "unexpected `20` after value",
+            ),
+            ("[[tortilla]]\ncenter = [1, 2", 2, 10, "unterminated array"),
+            (
+                "[[tortilla]]\ncenter = [1 2]",
+                2,
+                13,
+                "expected `,` or `]`, found `2`",
+            ),
+            (
+                "[window]\n  width   =   \"x\"",
+                2,
+                15,
+                "`width` expects an integer, found string",
+            ),
+            ("[window]\nheight = 0", 2, 10, "`height` must be positive"),
+            (
+                "[window]\nrefresh = -3",
+                2,
+                11,
+                "`refresh` cannot be negative",
+            ),
+            ("[window]\ncolor = 3", 2, 9, "unknown window field `color`"),
+        ];
+        for (text, line, column, message) in cases {
+            assert_eq!(
+                parse_error_at(text),
+                (line, column, message.to_string()),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn columns_count_characters() {
+        let (line, column, _) = parse_error_at("[window]\ntitle = \"ü\"  junk");
+        assert_eq!((line, column), (2, 14));
+    }
+
+    #[test]
+    fn structure_errors_point_at_the_line() {
+        let cases = [
+            ("width = 3", 1, 1, "field outside of a table"),
+            ("\n\n  [window\n", 3, 3, "unclosed table header"),
+            ("[[bad name]]", 1, 2, "invalid table name `bad name`"),
+            ("[window]\n[window]", 2, 1, "duplicate [window] table"),
+            (
+                "# comment\n[tortilla]",
+                2,
+                1,
+                "unknown table [tortilla], entities use [[tortilla]]",
+            ),
+            (
+                "[window]\nwidth = 1\nwidth = 2",
+                3,
+                1,
+                "duplicate key `width`",
+            ),
+            (
+                "[window]\n    just words",
+                2,
+                5,
+                "expected `key = value`, found `just words`",
+            ),
+            ("[window]\nbad key = 1", 2, 1, "invalid key `bad key`"),
+        ];
+        for (text, line, column, message) in cases {
+            assert_eq!(
+                parse_error_at(text),
+                (line, column, message.to_string()),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn entity_errors_point_at_their_field() {
+        let cases = [
+            ("[[blob]]", 1, 1, "unknown entity kind `blob`"),
+            (
+                "\n[[tortilla]]\ndamping = 3",
+                3,
+                11,
+                "`damping` must be within 0.8..1",
+            ),
+            (
+                "[[tortilla]]\nwobble = 1",
+                2,
+                10,
+                "unknown field `wobble` for tortilla",
+            ),
+            ("[[particles]]\ncount = 0", 2, 9, "`count` must be positive"),
+            (
+                "[[tortilla]]\nradius = 600.0",
+                2,
+                10,
+                "`radius` must be at most 200",
+            ),
+            (
+                "[[tortilla]]\ncell_size = 0",
+                2,
+                13,
+                "`cell_size` must be positive",
+            ),
+            (
+                "[[tortilla]]\nrigidity = -1",
+                2,
+                12,
+                "`rigidity` must be positive",
+            ),
+            (
+                "[[particles]]\nlayer = \"sky\"",
+                2,
+                9,
+                "unknown layer `sky`",
+            ),
+        ];
+        for (text, line, column, message) in cases {
+            let (found_line, found_column, found) = spawn_error_at(text);
+            assert_eq!((found_line, found_column), (line, column), "{text}");
+            assert!(found.starts_with(message), "{found}");
+        }
+    }
+
+    #[test]
+    fn particles_spawn_the_requested_count_anywhere() {
+        for anchor in ["[-100, 100]", "[5, 5]", "[600, 300]", "[0.5, -0.5]"] {
+            for count in [1, 2, 1600, 6400] {
+                let text = format!("[[particles]]\nanchor = {anchor}\ncount = {count}");
+                let scene = Scene::parse(&text, &[]).unwrap();
+                let entity = scene.entities[0].build(scene.grid_size()).unwrap();
+                let system = entity.as_any().downcast_ref::<ParticleSystem>().unwrap();
+                // the anchor is one of them
+                assert_eq!(system.len() + 1, count, "{anchor}");
+            }
+        }
+    }
+
+    #[test]
+    fn overrides_replace_fields_and_report_the_command_line() {
+        let overrides = [
+            Override::new("window", "width", Value::Int(320)),
+            Override::new("tortilla", "radius", Value::Str(String::from("big"))),
+        ];
+        let scene = Scene::parse("[[tortilla]]\nradius = 4.0", &overrides).unwrap();
+        assert_eq!(scene.window.width, 320);
+
+        let err = scene.spawn(&mut Entities::default()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`radius` expects a number, found string (set on the command line)"
+        );
+    }
+}
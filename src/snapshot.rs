@@ -0,0 +1,488 @@
+use crate::vector::Vector2D;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub const VERSION: u16 = 3;
+
+const MAGIC: &[u8; 8] = b"SLIMESNP";
+const TEXT_MAGIC: &str = "slime-snapshot";
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Int(u64),
+    Float(f32),
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    Format(String),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::Io(err) => write!(f, "snapshot io error: {err}"),
+            SnapshotError::Format(msg) => write!(f, "invalid snapshot: {msg}"),
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> Self {
+        SnapshotError::Io(err)
+    }
+}
+
+fn format_error<T>(msg: impl Into<String>) -> Result<T, SnapshotError> {
+    Err(SnapshotError::Format(msg.into()))
+}
+
+/// state of one entity: a kind tag and a flat list of values read back in
+/// the order they were pushed
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    kind: String,
+    values: Vec<Value>,
+    cursor: usize,
+}
+
+impl Record {
+    pub fn new(kind: &str) -> Self {
+        Self {
+            kind: kind.to_string(),
+            values: Vec::new(),
+            cursor: 0,
+        }
+    }
+
+    /// fail unless the record was saved by an entity of `kind`
+    pub fn expect_kind(&self, kind: &str) -> Result<(), SnapshotError> {
+        if self.kind == kind {
+            Ok(())
+        } else {
+            format_error(format!("expected {kind} record, found {}", self.kind))
+        }
+    }
+
+    pub fn push_bool(&mut self, value: bool) {
+        self.values.push(Value::Bool(value));
+    }
+
+    pub fn push_usize(&mut self, value: usize) {
+        self.values.push(Value::Int(value as u64));
+    }
+
+    pub fn push_f32(&mut self, value: f32) {
+        self.values.push(Value::Float(value));
+    }
+
+    pub fn push_vector(&mut self, value: Vector2D<f32>) {
+        self.push_f32(value.x);
+        self.push_f32(value.y);
+    }
+
+    fn next(&mut self) -> Result<Value, SnapshotError> {
+        match self.values.get(self.cursor) {
+            Some(&value) => {
+                self.cursor += 1;
+                Ok(value)
+            }
+            None => format_error(format!("{} record is too short", self.kind)),
+        }
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, SnapshotError> {
+        match self.next()? {
+            Value::Bool(value) => Ok(value),
+            other => format_error(format!("expected bool, found {other:?}")),
+        }
+    }
+
+    pub fn read_usize(&mut self) -> Result<usize, SnapshotError> {
+        match self.next()? {
+            Value::Int(value) => Ok(value as usize),
+            other => format_error(format!("expected int, found {other:?}")),
+        }
+    }
+
+    /// a count of entries that each take at least one value, so it can
+    /// never exceed the values left
+    pub fn read_len(&mut self) -> Result<usize, SnapshotError> {
+        let len = self.read_usize()?;
+        if len > self.values.len() - self.cursor {
+            return format_error(format!(
+                "{} record is too short for {len} entries",
+                self.kind
+            ));
+        }
+        Ok(len)
+    }
+
+    pub fn read_f32(&mut self) -> Result<f32, SnapshotError> {
+        match self.next()? {
+            Value::Float(value) => Ok(value),
+            other => format_error(format!("expected float, found {other:?}")),
+        }
+    }
+
+    pub fn read_vector(&mut self) -> Result<Vector2D<f32>, SnapshotError> {
+        Ok(Vector2D {
+            x: self.read_f32()?,
+            y: self.read_f32()?,
+        })
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], SnapshotError> {
+        if self.pos + n > self.bytes.len() {
+            return format_error("unexpected end of file");
+        }
+        let slice = &self.bytes[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, SnapshotError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, SnapshotError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, SnapshotError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, SnapshotError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Snapshot {
+    pub records: Vec<Record>,
+}
+
+impl Snapshot {
+    pub fn to_binary(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&(self.records.len() as u32).to_le_bytes());
+        for record in &self.records {
+            out.extend_from_slice(&(record.kind.len() as u16).to_le_bytes());
+            out.extend_from_slice(record.kind.as_bytes());
+            out.extend_from_slice(&(record.values.len() as u32).to_le_bytes());
+            for value in &record.values {
+                match *value {
+                    Value::Bool(value) => out.extend_from_slice(&[0, value as u8]),
+                    Value::Int(value) => {
+                        out.push(1);
+                        out.extend_from_slice(&value.to_le_bytes());
+                    }
+                    Value::Float(value) => {
+                        out.push(2);
+                        out.extend_from_slice(&value.to_le_bytes());
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    pub fn from_binary(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        let mut reader = Reader { bytes, pos: 0 };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return format_error("bad magic");
+        }
+        let version = reader.u16()?;
+        if version != VERSION {
+            return format_error(format!("unsupported version {version}"));
+        }
+
+        let mut records = Vec::new();
+        for _ in 0..reader.u32()? {
+            let len = reader.u16()? as usize;
+            let kind = String::from_utf8(reader.take(len)?.to_vec())
+                .or_else(|_| format_error("entity kind is not utf-8"))?;
+            let mut record = Record::new(&kind);
+            for _ in 0..reader.u32()? {
+                let value = match reader.u8()? {
+                    0 => Value::Bool(reader.u8()? != 0),
+                    1 => Value::Int(reader.u64()?),
+                    2 => Value::Float(f32::from_bits(reader.u32()?)),
+                    tag => return format_error(format!("unknown value tag {tag}")),
+                };
+                record.values.push(value);
+            }
+            records.push(record);
+        }
+        Ok(Self { records })
+    }
+
+    /// one value per line, `b`, `i` or `f` followed by the value
+    pub fn to_text(&self) -> String {
+        let mut out = format!("{TEXT_MAGIC} {VERSION}\n");
+        for record in &self.records {
+            out += &format!("entity {} {}\n", record.kind, record.values.len());
+            for value in &record.values {
+                match value {
+                    Value::Bool(value) => out += &format!("b {}\n", *value as u8),
+                    Value::Int(value) => out += &format!("i {value}\n"),
+                    Value::Float(value) => out += &format!("f {value}\n"),
+                }
+            }
+            out += "end\n";
+        }
+        out
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, SnapshotError> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty());
+
+        match lines.next() {
+            Some((_, header)) if header == format!("{TEXT_MAGIC} {VERSION}") => {}
+            Some((_, header)) => return format_error(format!("bad header `{header}`")),
+            None => return format_error("empty file"),
+        }
+
+        let mut records = Vec::new();
+        let mut current: Option<Record> = None;
+        let mut expected = 0;
+        for (n, line) in lines {
+            let (tag, rest) = line.split_once(' ').unwrap_or((line, ""));
+            let bad = || SnapshotError::Format(format!("line {n}: bad value `{line}`"));
+            match (tag, current.as_mut()) {
+                ("entity", None) => {
+                    let (kind, len) = rest.split_once(' ').ok_or_else(bad)?;
+                    current = Some(Record::new(kind));
+                    expected = len.parse().map_err(|_| bad())?;
+                }
+                ("end", Some(record)) => {
+                    if record.values.len() != expected {
+                        return format_error(format!(
+                            "line {n}: {} record has {} values, its header says {expected}",
+                            record.kind,
+                            record.values.len()
+                        ));
+                    }
+                    records.extend(current.take())
+                }
+                ("b", Some(record)) => {
+                    record.push_bool(rest.parse::<u8>().map_err(|_| bad())? != 0)
+                }
+                ("i", Some(record)) => record
+                    .values
+                    .push(Value::Int(rest.parse().map_err(|_| bad())?)),
+                ("f", Some(record)) => record.push_f32(rest.parse().map_err(|_| bad())?),
+                _ => return format_error(format!("line {n}: unexpected `{line}`")),
+            }
+        }
+        if current.is_some() {
+            return format_error("missing `end`");
+        }
+        Ok(Self { records })
+    }
+
+    fn is_text(path: &Path) -> bool {
+        path.extension().is_some_and(|ext| ext == "txt")
+    }
+
+    /// `.txt` files use the text format, anything else the binary one
+    pub fn save(&self, path: &Path) -> Result<(), SnapshotError> {
+        if Self::is_text(path) {
+            fs::write(path, self.to_text())?;
+        } else {
+            fs::write(path, self.to_binary())?;
+        }
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, SnapshotError> {
+        if Self::is_text(path) {
+            Self::from_text(&fs::read_to_string(path)?)
+        } else {
+            Self::from_binary(&fs::read(path)?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::Snapshotable;
+    use crate::tortilla::Tortilla;
+
+    fn sample() -> Snapshot {
+        let mut tortilla = Record::new("tortilla");
+        tortilla.push_usize(3);
+        tortilla.push_f32(-0.25);
+        tortilla.push_vector(Vector2D::new(1.5, f32::MAX));
+        tortilla.push_bool(true);
+        let mut hud = Record::new("hud");
+        hud.push_bool(false);
+        Snapshot {
+            records: vec![tortilla, hud, Record::new("empty")],
+        }
+    }
+
+    fn format_message<T: fmt::Debug>(result: Result<T, SnapshotError>) -> String {
+        match result {
+            Err(SnapshotError::Format(message)) => message,
+            other => panic!("expected a format error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn binary_round_trips() {
+        let snapshot = sample();
+        assert_eq!(
+            Snapshot::from_binary(&snapshot.to_binary()).unwrap(),
+            snapshot
+        );
+    }
+
+    #[test]
+    fn text_round_trips() {
+        let snapshot = sample();
+        assert_eq!(Snapshot::from_text(&snapshot.to_text()).unwrap(), snapshot);
+    }
+
+    #[test]
+    fn records_read_back_in_order() {
+        let mut record = sample().records.remove(0);
+        record.expect_kind("tortilla").unwrap();
+        assert_eq!(record.read_usize().unwrap(), 3);
+        assert_eq!(record.read_f32().unwrap(), -0.25);
+        let vector = record.read_vector().unwrap();
+        assert_eq!((vector.x, vector.y), (1.5, f32::MAX));
+        assert!(record.read_bool().unwrap());
+        assert!(record.read_bool().is_err());
+        assert!(Record::new("hud").expect_kind("tortilla").is_err());
+    }
+
+    #[test]
+    fn wrong_value_type_is_an_error() {
+        let mut record = sample().records.remove(0);
+        assert!(format_message(record.read_f32()).contains("expected float"));
+    }
+
+    #[test]
+    fn lengths_past_the_record_are_an_error() {
+        let mut record = Record::new("particles");
+        record.push_usize(4_000_000_000_000_000_000);
+        record.push_f32(0.0);
+        assert!(format_message(record.read_len()).contains("too short"));
+
+        let mut record = Record::new("particles");
+        record.push_usize(1);
+        record.push_f32(0.0);
+        assert_eq!(record.read_len().unwrap(), 1);
+    }
+
+    #[test]
+    fn truncated_binary_is_an_error() {
+        let bytes = sample().to_binary();
+        for len in 0..bytes.len() {
+            assert!(Snapshot::from_binary(&bytes[..len]).is_err(), "{len} bytes");
+        }
+    }
+
+    #[test]
+    fn bad_binary_headers_are_errors() {
+        let mut bytes = sample().to_binary();
+        bytes[0] = b'X';
+        assert_eq!(format_message(Snapshot::from_binary(&bytes)), "bad magic");
+
+        let mut bytes = sample().to_binary();
+        bytes[MAGIC.len()..MAGIC.len() + 2].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        assert!(format_message(Snapshot::from_binary(&bytes)).contains("unsupported version"));
+    }
+
+    #[test]
+    fn bad_value_tag_is_an_error() {
+        let mut bytes = sample().to_binary();
+        // first value tag: magic, version, record count, kind length, kind, value count
+        let tag = MAGIC.len() + 2 + 4 + 2 + "tortilla".len() + 4;
+        assert_eq!(bytes[tag], 1);
+        bytes[tag] = 9;
+        assert_eq!(
+            format_message(Snapshot::from_binary(&bytes)),
+            "unknown value tag 9"
+        );
+    }
+
+    #[test]
+    fn bad_text_is_an_error() {
+        let text = sample().to_text();
+        assert!(format_message(Snapshot::from_text("")).contains("empty"));
+        assert!(format_message(Snapshot::from_text("slime-snapshot 0\n")).contains("bad header"));
+
+        let missing_end = text.trim_end().strip_suffix("end").unwrap();
+        assert!(format_message(Snapshot::from_text(missing_end)).contains("missing `end`"));
+
+        let bad_tag = text.replacen("i 3", "x 3", 1);
+        assert_eq!(
+            format_message(Snapshot::from_text(&bad_tag)),
+            "line 3: unexpected `x 3`"
+        );
+
+        let bad_value = text.replacen("f -0.25", "f soft", 1);
+        assert_eq!(
+            format_message(Snapshot::from_text(&bad_value)),
+            "line 4: bad value `f soft`"
+        );
+    }
+
+    #[test]
+    fn text_value_count_must_match() {
+        let text = sample()
+            .to_text()
+            .replacen("entity tortilla 5", "entity tortilla 6", 1);
+        assert!(format_message(Snapshot::from_text(&text)).contains("its header says 6"));
+
+        let text = sample().to_text().replacen("entity hud 1", "entity hud", 1);
+        assert!(format_message(Snapshot::from_text(&text)).contains("bad value"));
+    }
+
+    #[test]
+    fn tortilla_links_must_point_forward() {
+        let tortilla = Tortilla::new(
+            Vector2D::new(16, 16),
+            Vector2D::new(20.0, 20.0),
+            1.0,
+            1.0,
+            10,
+            3.0,
+        );
+        let record = tortilla.save();
+        assert!(tortilla.restore(&mut record.clone()).is_ok());
+
+        // params, radius, pinch and cell count, then the first cell: position,
+        // speed, size, fix and link count before its first link
+        let first_link = 7 + 7;
+        for id in [0, 1_000_000] {
+            let mut record = record.clone();
+            assert!(matches!(record.values[first_link], Value::Int(1..)));
+            record.values[first_link] = Value::Int(id);
+            assert!(
+                format_message(tortilla.restore(&mut record).map(drop)).contains("out of range")
+            );
+        }
+    }
+}
@@ -31,19 +31,25 @@ impl SpatialGrid {
         }
     }
 
-    fn ratio_to_tile(&self, n: f32) -> usize {
-        (n / self.tile_size).floor() as usize
+    pub fn tile_size(&self) -> f32 {
+        self.tile_size
     }
 
-    fn pos_to_tile(&self, pos: Vector2D<f32>) -> Vector2D<usize> {
+    /// tiles left of or above the origin get negative coordinates
+    fn ratio_to_tile(&self, n: f32) -> isize {
+        (n / self.tile_size).floor() as isize
+    }
+
+    /// coordinates of the tile holding `pos`, before hashing
+    pub fn pos_to_tile(&self, pos: Vector2D<f32>) -> Vector2D<isize> {
         Vector2D {
             x: self.ratio_to_tile(pos.x),
             y: self.ratio_to_tile(pos.y),
         }
     }
 
-    fn hash_tile(&self, tile: Vector2D<usize>) -> usize {
-        tile.x.wrapping_mul(self.hash.x) ^ tile.y.wrapping_mul(self.hash.y)
+    fn hash_tile(&self, tile: Vector2D<isize>) -> usize {
+        (tile.x as usize).wrapping_mul(self.hash.x) ^ (tile.y as usize).wrapping_mul(self.hash.y)
     }
 
     fn get_index(&self, hashed: usize) -> usize {
@@ -83,10 +89,7 @@ impl SpatialGrid {
     }
 
     fn get_tile(&self, index: usize) -> Option<&Vec<usize>> {
-        let tile: &Tile = match self.grid.get(index) {
-            Some(tile) => tile,
-            None => return None,
-        };
+        let tile: &Tile = self.grid.get(index)?;
         if tile.stamp != self.current_stamp {
             None
         } else {
@@ -112,4 +115,11 @@ impl SpatialGrid {
         }
         sum
     }
+
+    /// ids pushed on the tile holding `pos`, ids of other tiles sharing its
+    /// hash can show up too
+    pub fn get_at(&self, pos: Vector2D<f32>) -> &[usize] {
+        self.get_tile(self.pos_to_index(pos))
+            .map_or(&[], |tile| tile.as_slice())
+    }
 }
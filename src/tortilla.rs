@@ -1,16 +1,72 @@
-use crate::entity::{Drawable, Entity, Inputable, Updatable};
+use crate::camera::View;
+use crate::color::Color;
+use crate::debug;
+use crate::delaunay;
+use crate::entities::Commands;
+use crate::entity::{Drawable, Entity, Inputable, Restore, Snapshotable, Updatable, restore_with};
 use crate::frame::Frame;
 use crate::input::Input;
+use crate::param::Param;
 use crate::rgb;
+use crate::snapshot::{Record, SnapshotError};
 use crate::spatial_grid::SpatialGrid;
 use crate::vector::Vector2D;
 
 const X_HASH: usize = 6287364878;
 const Y_HASH: usize = 2731859790;
+const MAX_RECOVERY_SPEED: usize = 50;
+const REST_COLOR: Color = Color::rgb(190, 190, 150);
+const STRETCHED_COLOR: Color = Color::rgb(110, 80, 45);
+const COMPRESSED_COLOR: Color = Color::rgb(235, 230, 195);
+const OUTLINE_COLOR: Color = Color::rgb(245, 235, 200);
+/// stretch at which a triangle reaches `STRETCHED_COLOR`
+const FULL_STRETCH: f32 = 1.5;
+/// compression at which a triangle reaches `COMPRESSED_COLOR`
+const FULL_COMPRESSION: f32 = 0.7;
+/// triangles stretched past this are torn and leave a hole
+const TEAR_STRETCH: f32 = 2.0;
+
+/// tunable physics of a `Tortilla`
+#[derive(Copy, Clone, Debug)]
+pub struct TortillaParams {
+    /// solver iterations per step, higher recovers the shape faster
+    pub recovery_speed: usize,
+    /// speed kept after each step
+    pub damping: f32,
+    /// how hard the area constraint pushes back to the rest radius
+    pub area_stiffness: f32,
+}
+
+impl TortillaParams {
+    fn params_mut(&mut self) -> Vec<Param<'_>> {
+        vec![
+            Param::int("recovery", &mut self.recovery_speed, 1, MAX_RECOVERY_SPEED),
+            Param::float("damping", &mut self.damping, 0.8, 1.0),
+            Param::float("stiffness", &mut self.area_stiffness, 0.0, 1.0),
+        ]
+    }
+}
+
+impl Default for TortillaParams {
+    fn default() -> Self {
+        Self {
+            recovery_speed: 10,
+            damping: 0.95,
+            area_stiffness: 0.2,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum RenderMode {
+    Points,
+    Mesh,
+}
 
 #[derive(Default, Clone, Debug)]
 struct TortillaCell {
     pos: Vector2D<f32>,
+    prev: Vector2D<f32>,
     speed: Vector2D<f32>,
     size: f32,
     fix: bool,
@@ -21,6 +77,7 @@ impl TortillaCell {
     fn new(x: f32, y: f32, size: f32) -> Self {
         Self {
             pos: Vector2D::new(x, y),
+            prev: Vector2D::new(x, y),
             speed: Vector2D { x: 0.0, y: 0.0 },
             size,
             fix: false,
@@ -28,69 +85,178 @@ impl TortillaCell {
         }
     }
 
-    fn apply_gravity(&mut self, anchor: Vector2D<f32>, dt: f32) {
-        if !self.fix {
-            // simulate simple gravity
-            let dir = Vector2D {
-                x: anchor.x - self.pos.x,
-                y: anchor.y - self.pos.y,
-            };
-
-            let dist_sq: f32 = dir.x * dir.x + dir.y * dir.y;
-
-            let a = 1000.0;
-            if dist_sq != 0.0 {
-                let dist = dist_sq.sqrt();
-                let normale = Vector2D {
-                    x: dir.x / dist,
-                    y: dir.y / dist,
-                };
-                let velocity = Vector2D {
-                    x: normale.x * a,
-                    y: normale.y * a,
-                };
-                self.speed.x += velocity.x * dt;
-                self.speed.y += velocity.y * dt;
-            }
+    fn save(&self, record: &mut Record) {
+        record.push_vector(self.pos);
+        record.push_vector(self.speed);
+        record.push_f32(self.size);
+        record.push_bool(self.fix);
+        record.push_usize(self.links.len());
+        for &(id, rest_length) in &self.links {
+            record.push_usize(id);
+            record.push_f32(rest_length);
         }
+    }
 
-        let damping = 0.95;
-        self.speed.x *= damping;
-        self.speed.y *= damping;
+    fn restore(record: &mut Record) -> Result<Self, SnapshotError> {
+        let pos = record.read_vector()?;
+        let mut cell = Self {
+            pos,
+            prev: pos,
+            speed: record.read_vector()?,
+            size: record.read_f32()?,
+            fix: record.read_bool()?,
+            links: Vec::new(),
+        };
+        for _ in 0..record.read_len()? {
+            cell.links.push((record.read_usize()?, record.read_f32()?));
+        }
+        Ok(cell)
     }
 
-    fn update(&mut self, dt: f32) {
+    fn update(&mut self, damping: f32, dt: f32) {
         if !self.fix {
             self.pos.x += self.speed.x * dt;
             self.pos.y += self.speed.y * dt;
         }
+
+        self.speed.x *= damping;
+        self.speed.y *= damping;
     }
 }
 
 impl Drawable for TortillaCell {
-    fn draw(&self, frame: &mut Frame) {
-        if self.pos.x >= 0.0
-            && self.pos.y >= 0.0
-            && (self.pos.x as usize) < frame.width
-            && (self.pos.y as usize) < frame.height
-        {
-            // if self.fix {
-            //     frame.buffer[self.pos.y as usize * frame.width + self.pos.x as usize] =
-            //         rgb!(255, 0, 0);
-            // } else {
-            frame.buffer[self.pos.y as usize * frame.width + self.pos.x as usize] =
-                rgb!(190, 190, 150);
-            // }
+    fn draw(&self, frame: &mut Frame, view: &View) {
+        let pos = view
+            .camera
+            .world_to_screen(self.prev.lerp(self.pos, view.alpha));
+        frame.fill_circle(
+            pos,
+            self.size * view.camera.zoom,
+            Color::rgba(190, 190, 150, 200),
+        );
+    }
+}
+
+struct MeshTriangle {
+    cells: [usize; 3],
+    /// rest length of the edges, edge `i` going from cell `i` to the next
+    rest: [f32; 3],
+}
+
+/// triangulation of the rest configuration, drawn deformed
+struct Mesh {
+    triangles: Vec<MeshTriangle>,
+    /// edges used by a single triangle
+    boundary: Vec<(usize, usize)>,
+}
+
+impl Mesh {
+    /// only triangles whose edges are all short enough to be links are kept,
+    /// the hull of the delaunay triangulation is not part of the tortilla
+    fn new(cells: &[TortillaCell]) -> Self {
+        let reach = cells
+            .iter()
+            .flat_map(|cell| cell.links.iter())
+            .map(|&(_, length)| length)
+            .fold(0.0, f32::max)
+            * (1.0 + 1e-4);
+        let points: Vec<_> = cells.iter().map(|cell| cell.pos).collect();
+
+        let mut triangles = Vec::new();
+        for vertices in delaunay::triangulate(&points) {
+            let rest = [0, 1, 2].map(|i| {
+                points[vertices[i]]
+                    .delta(points[vertices[(i + 1) % 3]])
+                    .length()
+            });
+            if rest.iter().all(|&length| length > 0.0 && length <= reach) {
+                triangles.push(MeshTriangle {
+                    cells: vertices,
+                    rest,
+                });
+            }
+        }
+
+        let mut edges: Vec<(usize, usize)> = triangles
+            .iter()
+            .flat_map(|triangle| {
+                let [a, b, c] = triangle.cells;
+                [(a, b), (b, c), (c, a)].map(|(a, b)| (a.min(b), a.max(b)))
+            })
+            .collect();
+        edges.sort_unstable();
+        let boundary = edges
+            .chunk_by(|a, b| a == b)
+            .filter(|run| run.len() == 1)
+            .map(|run| run[0])
+            .collect();
+
+        Self {
+            triangles,
+            boundary,
         }
     }
+
+    /// `world` are the cell positions, `screen` the same ones projected
+    fn draw(
+        &self,
+        frame: &mut Frame,
+        world: &[Vector2D<f32>],
+        screen: &[Vector2D<f32>],
+        outline: bool,
+    ) {
+        for triangle in &self.triangles {
+            let mut stretch = 0.0;
+            for i in 0..3 {
+                let (a, b) = (triangle.cells[i], triangle.cells[(i + 1) % 3]);
+                stretch += world[a].delta(world[b]).length() / triangle.rest[i];
+            }
+            let stretch = stretch / 3.0;
+            if stretch > TEAR_STRETCH {
+                continue;
+            }
+
+            let color = if stretch >= 1.0 {
+                REST_COLOR.lerp(
+                    STRETCHED_COLOR,
+                    ((stretch - 1.0) / (FULL_STRETCH - 1.0)).min(1.0),
+                )
+            } else {
+                REST_COLOR.lerp(
+                    COMPRESSED_COLOR,
+                    ((1.0 - stretch) / (1.0 - FULL_COMPRESSION)).min(1.0),
+                )
+            };
+            frame.fill_polygon(&triangle.cells.map(|id| screen[id]), color);
+        }
+
+        if outline {
+            for &(a, b) in &self.boundary {
+                frame.line_aa(screen[a], screen[b], OUTLINE_COLOR);
+            }
+        }
+    }
+}
+
+/// the links hold the rest lengths, cells linked the same way share the
+/// rest configuration the mesh is built from
+fn same_rest_layout(cells: &[TortillaCell], other: &[TortillaCell]) -> bool {
+    cells.len() == other.len()
+        && cells
+            .iter()
+            .zip(other)
+            .all(|(cell, other)| cell.links == other.links)
 }
 
 pub struct Tortilla {
     cells: Vec<TortillaCell>,
     pinch: Option<usize>,
     grid: SpatialGrid,
-    recovery_speed: usize,
+    params: TortillaParams,
     radius: f32,
+    mesh: Mesh,
+    render: RenderMode,
+    outline: bool,
 }
 
 impl Tortilla {
@@ -115,7 +281,7 @@ impl Tortilla {
                 let dx = x - center.x;
                 let dy = y - center.y;
 
-                if dx * dx + dy * dy <= radius as f32 * radius as f32 {
+                if dx * dx + dy * dy <= radius * radius {
                     cells.push(TortillaCell::new(
                         (origin.x + offset_x as isize) as f32,
                         (origin.y + offset_y as isize) as f32,
@@ -137,21 +303,26 @@ impl Tortilla {
         }
         avg_radius /= cells.len() as f32;
 
+        // cells come row by row, so past the reach of a cell below it the
+        // rest of the cells are out of reach too
+        let reach = cell_size * 2.0 * rigidity;
         let cell_nb = cells.len();
         for i in 0..cell_nb {
             for j in (i + 1)..cell_nb {
                 let (left, right) = cells.split_at_mut(j);
                 let first = &mut left[i];
                 let second = &mut right[0];
+                if second.pos.y - first.pos.y > reach {
+                    break;
+                }
                 let length = first.pos.delta(second.pos).length();
-                if length <= (first.size * 2.0) * rigidity {
+                if length <= reach {
                     first.links.push((j, length));
                 }
             }
         }
 
         Self {
-            cells,
             pinch: None,
             grid: SpatialGrid::new(
                 grid_size.x * grid_size.y,
@@ -161,11 +332,61 @@ impl Tortilla {
                     y: Y_HASH,
                 },
             ),
-            recovery_speed,
+            params: TortillaParams {
+                recovery_speed,
+                ..TortillaParams::default()
+            },
             radius: avg_radius,
+            mesh: Mesh::new(&cells),
+            cells,
+            render: RenderMode::Mesh,
+            outline: true,
         }
     }
 
+    pub fn with_params(mut self, params: TortillaParams) -> Self {
+        self.params = params;
+        self
+    }
+
+    pub fn params(&self) -> TortillaParams {
+        self.params
+    }
+
+    pub fn set_params(&mut self, params: TortillaParams) {
+        self.params = params;
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = Vector2D<f32>> + '_ {
+        self.cells.iter().map(|cell| cell.pos)
+    }
+
+    /// mean position of the cells
+    pub fn center(&self) -> Vector2D<f32> {
+        self.positions()
+            .fold(Vector2D::default(), |sum, pos| sum.add(pos))
+            .vdiv(self.cells.len() as f32)
+    }
+
+    /// mean distance of the cells to the center that the area solver
+    /// restores
+    pub fn rest_radius(&self) -> f32 {
+        self.radius
+    }
+
+    /// index of the cell held by the pinch tool
+    pub fn pinch(&self) -> Option<usize> {
+        self.pinch
+    }
+
     fn set_pinch(&mut self, i: Option<usize>) {
         if let Some(n) = self.pinch {
             self.cells[n].fix = false;
@@ -187,23 +408,51 @@ impl Tortilla {
 }
 
 impl Inputable for Tortilla {
-    fn handle_input(&mut self, input: Input) {
-        if input.mouse.left {
-            match self.pinch_cell() {
-                Some(cell) => cell.pos = input.mouse.pos,
-                None => {
-                    let list = self.grid.get(input.mouse.pos, 0.1);
-                    self.set_pinch(None);
-                    for id in list {
-                        let cell = &self.cells[id];
-                        if cell.pos.delta(input.mouse.pos).length() < cell.size {
-                            self.set_pinch(Some(id));
-                            break;
-                        }
-                    }
+    fn handle_input(&mut self, input: &Input) {
+        if input.action("tortilla_render").pressed {
+            self.render = match self.render {
+                RenderMode::Points => RenderMode::Mesh,
+                RenderMode::Mesh => RenderMode::Points,
+            };
+        }
+        if input.action("outline").pressed {
+            self.outline = !self.outline;
+        }
+
+        if input.action("pinch").pressed {
+            let list = self.grid.get(input.mouse.world_pos, 0.1);
+            self.set_pinch(None);
+            for id in list {
+                let cell = &self.cells[id];
+                if cell.pos.delta(input.mouse.world_pos).length() < cell.size {
+                    self.set_pinch(Some(id));
+                    break;
                 }
             }
-        } else {
+        }
+
+        if input.action("pinch").held {
+            if let Some(cell) = self.pinch_cell() {
+                cell.pos = input.mouse.world_pos;
+                cell.prev = input.mouse.world_pos;
+            }
+
+            // scrolling while pinching tunes how fast the shape recovers
+            let steps = input.mouse.wheel.y.round() as isize;
+            self.params.recovery_speed = self
+                .params
+                .recovery_speed
+                .saturating_add_signed(steps)
+                .clamp(1, MAX_RECOVERY_SPEED);
+        } else if input.action("pinch").released && self.pinch.is_some() {
+            // throw the whole tortilla with the velocity of the release
+            for cell in &mut self.cells {
+                cell.speed = input.mouse.world_velocity;
+            }
+        }
+
+        if !input.action("pinch").held && !input.action("pin").held {
+            // pin keeps the pinched cell in place after the button is released
             self.set_pinch(None);
         }
     }
@@ -263,7 +512,7 @@ impl Tortilla {
         avg_radius /= self.cells.len() as f32;
 
         let compression = self.radius - avg_radius;
-        let stiffness = 0.2;
+        let stiffness = self.params.area_stiffness;
 
         for cell in &mut self.cells {
             let dir = cell.pos.sub(center).normalize();
@@ -273,13 +522,15 @@ impl Tortilla {
 }
 
 impl Updatable for Tortilla {
-    fn update(&mut self, dt: f32) {
+    fn update(&mut self, dt: f32, _commands: &mut Commands) {
+        self.grid.clear();
         for (id, cell) in self.cells.iter_mut().enumerate() {
-            cell.update(dt);
+            cell.prev = cell.pos;
+            cell.update(self.params.damping, dt);
             self.grid.push(id, cell.pos, cell.size);
         }
 
-        for _ in 0..self.recovery_speed {
+        for _ in 0..self.params.recovery_speed {
             self.solve_length_links();
             self.solve_area();
         }
@@ -287,11 +538,213 @@ impl Updatable for Tortilla {
 }
 
 impl Drawable for Tortilla {
-    fn draw(&self, frame: &mut Frame) {
+    fn draw(&self, frame: &mut Frame, view: &View) {
+        let world: Vec<_> = self
+            .cells
+            .iter()
+            .map(|cell| cell.prev.lerp(cell.pos, view.alpha))
+            .collect();
+        let screen: Vec<_> = world
+            .iter()
+            .map(|&pos| view.camera.world_to_screen(pos))
+            .collect();
+
+        match self.render {
+            RenderMode::Points => {
+                // links are stored on the lower index cell only
+                for (i, cell) in self.cells.iter().enumerate() {
+                    for &(j, _) in &cell.links {
+                        frame.line_aa(screen[i], screen[j], rgb!(90, 90, 70));
+                    }
+                }
+                for cell in &self.cells {
+                    cell.draw(frame, view);
+                }
+            }
+            RenderMode::Mesh => self.mesh.draw(frame, &world, &screen, self.outline),
+        }
+    }
+
+    fn draw_debug(&self, frame: &mut Frame, view: &View) {
+        let world: Vec<_> = self
+            .cells
+            .iter()
+            .map(|cell| cell.prev.lerp(cell.pos, view.alpha))
+            .collect();
+        for (i, cell) in self.cells.iter().enumerate() {
+            for &(j, rest_length) in &cell.links {
+                frame.line(
+                    view.camera.world_to_screen(world[i]),
+                    view.camera.world_to_screen(world[j]),
+                    debug::strain_color(world[i].delta(world[j]).length(), rest_length)
+                        .with_alpha(160),
+                );
+            }
+        }
+
+        debug::draw_tiles(
+            frame,
+            view,
+            &self.grid,
+            self.cells.iter().map(|cell| cell.pos),
+        );
+
+        if let Some(&pos) = self.pinch.and_then(|i| world.get(i)) {
+            let pos = view.camera.world_to_screen(pos);
+            debug::draw_marker(frame, pos, 5.0, Color::rgb(255, 0, 255));
+        }
+    }
+}
+
+impl Snapshotable for Tortilla {
+    fn save(&self) -> Record {
+        let mut record = Record::new("tortilla");
+        record.push_usize(self.params.recovery_speed);
+        record.push_f32(self.params.damping);
+        record.push_f32(self.params.area_stiffness);
+        record.push_f32(self.radius);
+        record.push_bool(self.pinch.is_some());
+        record.push_usize(self.pinch.unwrap_or(0));
+        record.push_usize(self.cells.len());
         for cell in &self.cells {
-            cell.draw(frame);
+            cell.save(&mut record);
         }
+        record
+    }
+
+    fn restore(&self, record: &mut Record) -> Result<Restore, SnapshotError> {
+        record.expect_kind("tortilla")?;
+        let mut params = TortillaParams {
+            recovery_speed: record.read_usize()?,
+            damping: record.read_f32()?,
+            area_stiffness: record.read_f32()?,
+        };
+        if let Some(param) = params.params_mut().iter().find(|param| !param.in_range()) {
+            return Err(SnapshotError::Format(format!(
+                "tortilla {} out of range",
+                param.name
+            )));
+        }
+        let radius = record.read_f32()?;
+        let pinched = record.read_bool()?;
+        let pinch = record.read_usize()?;
+        let cell_nb = record.read_len()?;
+        let mut cells = Vec::with_capacity(cell_nb);
+        for _ in 0..cell_nb {
+            cells.push(TortillaCell::restore(record)?);
+        }
+
+        // links are kept by the lower of their two cells
+        let linked_out = cells
+            .iter()
+            .enumerate()
+            .any(|(i, cell)| cell.links.iter().any(|&(id, _)| id <= i || id >= cell_nb));
+        if linked_out || (pinched && pinch >= cell_nb) {
+            return Err(SnapshotError::Format(String::from(
+                "tortilla index out of range",
+            )));
+        }
+
+        Ok(restore_with(move |tortilla: &mut Self| {
+            tortilla.params = params;
+            tortilla.radius = radius;
+            tortilla.pinch = if pinched { Some(pinch) } else { None };
+            if !same_rest_layout(&tortilla.cells, &cells) {
+                // the rest configuration is gone, the restored shape stands in
+                tortilla.mesh = Mesh::new(&cells);
+            }
+            tortilla.cells = cells;
+        }))
     }
 }
 
-impl Entity for Tortilla {}
+impl Entity for Tortilla {
+    fn name(&self) -> &'static str {
+        "tortilla"
+    }
+
+    fn inspect(&self) -> Vec<(&'static str, String)> {
+        let links: usize = self.cells.iter().map(|cell| cell.links.len()).sum();
+        vec![
+            ("cells", self.cells.len().to_string()),
+            ("links", links.to_string()),
+            ("recovery", self.params.recovery_speed.to_string()),
+            ("radius", format!("{:.1}", self.radius)),
+            ("render", format!("{:?}", self.render).to_lowercase()),
+        ]
+    }
+
+    fn params_mut(&mut self) -> Vec<Param<'_>> {
+        let mut params = self.params.params_mut();
+        params.push(Param::toggle("outline", &mut self.outline));
+        params
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tortilla(radius: f32) -> Tortilla {
+        Tortilla::new(
+            Vector2D::new(64, 64),
+            Vector2D::new(100.0, 100.0),
+            1.0,
+            1.0,
+            10,
+            radius,
+        )
+    }
+
+    fn restore(tortilla: &mut Tortilla, mut record: Record) {
+        let apply = tortilla.restore(&mut record).unwrap();
+        apply(tortilla);
+    }
+
+    #[test]
+    fn params_out_of_range_are_a_restore_error() {
+        let mut tortilla = tortilla(5.0);
+        tortilla.params.recovery_speed = 0;
+        // the panel still shows a value under its range
+        assert_eq!(tortilla.params_mut()[0].ratio(), 0.0);
+
+        let mut record = tortilla.save();
+        match tortilla.restore(&mut record) {
+            Err(SnapshotError::Format(message)) => {
+                assert_eq!(message, "tortilla recovery out of range")
+            }
+            _ => panic!("recovery 0 restored"),
+        }
+    }
+
+    #[test]
+    fn restoring_another_layout_rebuilds_the_mesh() {
+        let large = tortilla(20.0);
+        let mut small = tortilla(10.0);
+        restore(&mut small, large.save());
+
+        assert_eq!(small.len(), large.len());
+        let mut covered = vec![false; small.len()];
+        for triangle in &small.mesh.triangles {
+            for &id in &triangle.cells {
+                covered[id] = true;
+            }
+        }
+        assert!(covered.iter().all(|&covered| covered));
+        assert_eq!(small.mesh.triangles.len(), large.mesh.triangles.len());
+    }
+
+    #[test]
+    fn restoring_the_same_layout_keeps_the_rest_mesh() {
+        let mut stretched = tortilla(5.0);
+        for cell in &mut stretched.cells {
+            cell.pos = cell.pos.vmul(2.0);
+        }
+
+        let mut restored = tortilla(5.0);
+        let rest: Vec<_> = restored.mesh.triangles.iter().map(|t| t.rest).collect();
+        restore(&mut restored, stretched.save());
+        let kept: Vec<_> = restored.mesh.triangles.iter().map(|t| t.rest).collect();
+        assert_eq!(kept, rest);
+    }
+}
@@ -0,0 +1,185 @@
+use crate::color::Color;
+use crate::entities::{Entities, EntityId};
+use crate::font;
+use crate::frame::Frame;
+use crate::input::InputState;
+use crate::param::{Param, ParamValue};
+use crate::vector::Vector2D;
+
+const MARGIN: f32 = 8.0;
+const PADDING: f32 = 6.0;
+const ROW_HEIGHT: f32 = 14.0;
+const LABEL_WIDTH: f32 = (12 * font::ADVANCE) as f32;
+const SLIDER_WIDTH: f32 = 100.0;
+const VALUE_WIDTH: f32 = (7 * font::ADVANCE) as f32;
+const GAP: f32 = 6.0;
+const WIDTH: f32 = PADDING * 2.0 + LABEL_WIDTH + SLIDER_WIDTH + GAP + VALUE_WIDTH;
+
+const BACKGROUND: Color = Color::rgba(20, 20, 30, 200);
+const HEADER_COLOR: Color = Color::rgb(255, 220, 120);
+const LABEL_COLOR: Color = Color::rgb(200, 200, 200);
+const TRACK_COLOR: Color = Color::rgb(60, 60, 75);
+const FILL_COLOR: Color = Color::rgb(90, 150, 230);
+const ACTIVE_COLOR: Color = Color::rgb(140, 200, 255);
+
+/// entity and index of one of its params
+type ParamId = (EntityId, usize);
+
+enum Row<'r, 'a> {
+    Header(&'static str),
+    Param(ParamId, &'r mut Param<'a>),
+}
+
+/// calls `visit` with the top of every row, returns the total height
+fn walk(entities: &mut Entities, mut visit: impl FnMut(f32, Row)) -> f32 {
+    let mut y = PADDING;
+    for (id, entity) in entities.iter_mut_with_ids() {
+        let name = entity.name();
+        let mut params = entity.params_mut();
+        if params.is_empty() {
+            continue;
+        }
+        visit(y, Row::Header(name));
+        y += ROW_HEIGHT;
+        for (j, param) in params.iter_mut().enumerate() {
+            visit(y, Row::Param((id, j), param));
+            y += ROW_HEIGHT;
+        }
+        y += ROW_HEIGHT / 2.0;
+    }
+    y + PADDING - ROW_HEIGHT / 2.0
+}
+
+/// (top left, size) of the control of the row starting at `y`
+fn control_rect(origin: Vector2D<f32>, y: f32, param: &Param) -> (Vector2D<f32>, Vector2D<f32>) {
+    let pos = Vector2D::new(origin.x + PADDING + LABEL_WIDTH, origin.y + y + 2.0);
+    match param.value {
+        ParamValue::Bool(_) => (pos, Vector2D::new(ROW_HEIGHT - 4.0, ROW_HEIGHT - 4.0)),
+        _ => (pos, Vector2D::new(SLIDER_WIDTH, ROW_HEIGHT - 4.0)),
+    }
+}
+
+fn inside(pos: Vector2D<f32>, (corner, size): (Vector2D<f32>, Vector2D<f32>)) -> bool {
+    pos.x >= corner.x && pos.y >= corner.y && pos.x < corner.x + size.x && pos.y < corner.y + size.y
+}
+
+/// immediate mode panel in the top right corner, rebuilt every frame from
+/// the `params_mut` of the entities
+#[derive(Default)]
+pub struct Panel {
+    visible: bool,
+    /// slider being dragged
+    active: Option<ParamId>,
+    was_down: bool,
+    /// a press started over the panel, the buttons stay with it until they
+    /// are all released
+    grabbed: bool,
+    any_was_down: bool,
+    /// of the last layout, for hovering
+    height: f32,
+}
+
+impl Panel {
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+        self.active = None;
+        self.grabbed = false;
+    }
+
+    fn origin(screen_width: usize) -> Vector2D<f32> {
+        Vector2D::new(screen_width as f32 - WIDTH - MARGIN, MARGIN)
+    }
+
+    /// the mouse buttons belong to the panel, entities should not see them;
+    /// a drag started elsewhere keeps going over the panel
+    pub fn captures(&self) -> bool {
+        self.visible && (self.grabbed || self.active.is_some())
+    }
+
+    pub fn interact(&mut self, state: &InputState, screen_width: usize, entities: &mut Entities) {
+        let any_down = state.mouse_left || state.mouse_middle || state.mouse_right;
+        if !any_down {
+            self.grabbed = false;
+        } else if !self.any_was_down {
+            let rect = (
+                Self::origin(screen_width),
+                Vector2D::new(WIDTH, self.height),
+            );
+            self.grabbed = self.visible && state.mouse_pos.is_some_and(|pos| inside(pos, rect));
+        }
+        self.any_was_down = any_down;
+
+        let down = state.mouse_left;
+        let pressed = down && !self.was_down;
+        self.was_down = down;
+        if !down {
+            self.active = None;
+        }
+        let (true, Some(mouse)) = (self.visible, state.mouse_pos) else {
+            return;
+        };
+
+        let origin = Self::origin(screen_width);
+        let mut active = self.active;
+        self.height = walk(entities, |y, row| {
+            let Row::Param(id, param) = row else {
+                return;
+            };
+            let rect = control_rect(origin, y, param);
+            if let ParamValue::Bool(value) = &mut param.value {
+                if pressed && inside(mouse, rect) {
+                    **value = !**value;
+                }
+                return;
+            }
+            if pressed && inside(mouse, rect) {
+                active = Some(id);
+            }
+            if active == Some(id) {
+                param.set_ratio((mouse.x - rect.0.x) / rect.1.x);
+            }
+        });
+        self.active = active;
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, entities: &mut Entities) {
+        if !self.visible {
+            return;
+        }
+        let origin = Self::origin(frame.width);
+        frame.fill_rect(origin, Vector2D::new(WIDTH, self.height), BACKGROUND);
+
+        let active = self.active;
+        let text_offset = (ROW_HEIGHT - font::GLYPH_HEIGHT as f32) / 2.0;
+        self.height = walk(entities, |y, row| {
+            let text_pos = Vector2D::new(origin.x + PADDING, origin.y + y + text_offset);
+            match row {
+                Row::Header(name) => frame.draw_text(text_pos, name, HEADER_COLOR),
+                Row::Param(id, param) => {
+                    frame.draw_text(text_pos, param.name, LABEL_COLOR);
+                    let (corner, size) = control_rect(origin, y, param);
+                    let fill = if active == Some(id) {
+                        ACTIVE_COLOR
+                    } else {
+                        FILL_COLOR
+                    };
+                    frame.fill_rect(corner, size, TRACK_COLOR);
+                    match param.value {
+                        ParamValue::Bool(&mut true) => {
+                            let inset = Vector2D::new(2.0, 2.0);
+                            frame.fill_rect(corner.add(inset), size.sub(inset.vmul(2.0)), fill);
+                        }
+                        ParamValue::Bool(_) => {}
+                        _ => {
+                            let width = (size.x * param.ratio()).round();
+                            frame.fill_rect(corner, Vector2D::new(width, size.y), fill);
+                        }
+                    }
+                    let value_pos =
+                        Vector2D::new(origin.x + WIDTH - PADDING - VALUE_WIDTH, text_pos.y);
+                    frame.draw_text(value_pos, &param.display(), LABEL_COLOR);
+                }
+            }
+        });
+    }
+}
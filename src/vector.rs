@@ -122,3 +122,15 @@ where
         }
     }
 }
+
+impl<T> Vector2D<T>
+where
+    T: Copy + Add<Output = T> + Sub<Output = T> + Mul<Output = T>,
+{
+    pub fn lerp(&self, other: Vector2D<T>, t: T) -> Vector2D<T> {
+        Vector2D {
+            x: self.x + (other.x - self.x) * t,
+            y: self.y + (other.y - self.y) * t,
+        }
+    }
+}